@@ -0,0 +1,2811 @@
+//! Application state: files, the loaded VCF, filters, modals and config.
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use fuzzy_matcher::{FuzzyMatcher, skim::SkimMatcherV2};
+use ratatui::{
+    layout::{Constraint, Position, Rect},
+    widgets::{ListState, TableState},
+};
+use std::{
+    cell::RefCell,
+    cmp::Reverse,
+    collections::{BTreeSet, HashMap, HashSet},
+    env, fs, io,
+    io::{Read, Write},
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc::{self, Receiver, TryRecvError},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+use walkdir::WalkDir;
+
+use crate::{
+    filter::{BedMode, FilterSet, IdPresence, filter_match},
+    input::{Action, KeyBinding, KeyMap},
+    vcf::{
+        COMMENT_PREFIXES, GtCounts, ParsedVcf, PosIndex, PosRange, VariantStats, VcfRecord,
+        chrom_counts, info_numbers, is_url, parse_bed, parse_info, parse_vcf, parse_vcf_bytes,
+        write_bed, write_vcf,
+    },
+};
+
+#[derive(Default)]
+pub struct App {
+    pub tabs: TabsState,
+    pub files: FileListState,
+    pub vcf: VcfState,
+    pub modal: Option<ModalState>,
+    pub config: Config,
+    pub pending_load: bool, // picked up by the main loop so it can draw progress
+    pub pending_editor: Option<(PathBuf, Option<usize>)>, // file and line for `$EDITOR`, run by the main loop
+    pub keymap: KeyMap,
+    pub needs_redraw: bool, // set whenever state changes; cleared after drawing
+    pub message: Option<(String, Instant)>, // status-bar toast and when it was raised
+    pub wrap_navigation: bool,
+    pub context_rows: usize, // records shown either side in the context panel
+    views: HashMap<PathBuf, (Option<usize>, usize)>, // selection and scroll offset per file
+    file_filters: HashMap<PathBuf, FilterSet>, // filters last used on each file
+    pub history: HashMap<ModalKind, Vec<String>>, // values entered per input modal
+    pub no_color: bool,      // NO_COLOR set or a dumb terminal; emphasis by modifiers only
+    pub delimiter: Option<char>, // from `--delimiter`; None is tab with a whitespace fallback
+    pub pending_key: Option<(KeyBinding, Instant)>, // first key of a chord like `gg`
+    pub recent: Vec<PathBuf>, // recently opened files, most recent first
+    pub percent_decode: bool, // show `%3A`-style escapes decoded in the detail panel
+    pub copy_limit: usize,   // most records `Y` puts on the clipboard
+    pub comment_prefixes: Vec<String>, // lines skipped as comments when parsing
+    pub export: Option<ExportJob>, // export being written in the background
+}
+
+/// `key = value` settings file at `$XDG_CONFIG_HOME/vcfscan/config` (or
+/// `~/.config/vcfscan/config`). Lines are kept as read so that saving one
+/// setting leaves comments and unrelated keys untouched.
+#[derive(Debug, Default)]
+pub struct Config {
+    pub path: Option<PathBuf>,
+    pub lines: Vec<String>,
+}
+
+impl Config {
+    pub fn load() -> Self {
+        let path = env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+            .map(|dir| dir.join("vcfscan").join("config"));
+        let lines = path
+            .as_ref()
+            .and_then(|p| fs::read_to_string(p).ok())
+            .map(|s| s.lines().map(str::to_owned).collect())
+            .unwrap_or_default();
+        Config { path, lines }
+    }
+
+    /// `comment_prefixes = a,b` from the config, else `COMMENT_PREFIXES`.
+    /// An empty value turns comment skipping off; blank lines are always
+    /// skipped.
+    pub fn comment_prefixes(&self) -> Vec<String> {
+        match self.get("comment_prefixes") {
+            Some(v) => v
+                .split(',')
+                .map(str::trim)
+                .filter(|p| !p.is_empty())
+                .map(String::from)
+                .collect(),
+            None => COMMENT_PREFIXES.iter().map(|p| p.to_string()).collect(),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.lines.iter().find_map(|line| {
+            let (k, v) = line.split_once('=')?;
+            (!line.trim_start().starts_with('#') && k.trim() == key).then(|| v.trim())
+        })
+    }
+
+    fn set(&mut self, key: &str, value: &str) {
+        let entry = format!("{} = {}", key, value);
+        let existing = self.lines.iter_mut().find(|line| {
+            !line.trim_start().starts_with('#')
+                && line.split_once('=').is_some_and(|(k, _)| k.trim() == key)
+        });
+        match existing {
+            Some(line) => *line = entry,
+            None => self.lines.push(entry),
+        }
+    }
+
+    /// `recent` next to the config file: recently opened files, one path
+    /// per line, most recent first.
+    fn recent_path(&self) -> Option<PathBuf> {
+        Some(self.path.as_ref()?.parent()?.join("recent"))
+    }
+
+    fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut contents = self.lines.join("\n");
+        contents.push('\n');
+        fs::write(path, contents)
+    }
+}
+
+/// How long a status-bar toast stays up without a keypress.
+const TOAST_DURATION: Duration = Duration::from_secs(3);
+
+/// How many recently opened files are remembered.
+const RECENT_LIMIT: usize = 10;
+
+/// Records copied as TSV at most, unless `copy_limit` is set in the config.
+const COPY_LIMIT: usize = 1000;
+
+/// Longest base64 payload sent in one OSC 52 sequence; xterm and tmux drop
+/// much larger ones.
+const COPY_MAX_ENCODED: usize = 100_000;
+
+/// Matches the head toggle keeps, unless `--head` or `head_limit` says.
+const HEAD_LIMIT: usize = 1000;
+
+/// Share of the variant tab's width, in percent, given to the filter column:
+/// the default, the bounds `<`/`>` keep it within, and their step.
+const FILTER_PANE: u16 = 30;
+const FILTER_PANE_BOUNDS: (u16, u16) = (15, 60);
+const FILTER_PANE_STEP: u16 = 5;
+
+/// Reads the `sort` config value, e.g. `QUAL desc`; `none` (or anything
+/// unrecognised) keeps file order.
+fn parse_sort(value: &str) -> Option<(Column, bool)> {
+    let mut words = value.split_whitespace();
+    let column = Column::from_label(words.next()?)?;
+    Some((column, words.next() == Some("desc")))
+}
+
+/// Cuts `text` back to the whole lines that fit in `max_bytes`; returns
+/// whether anything was cut.
+fn cut_to_lines(text: &mut String, max_bytes: usize) -> bool {
+    if text.len() <= max_bytes {
+        return false;
+    }
+    // Searching bytes, the cap may fall inside a multibyte character.
+    let end = text.as_bytes()[..max_bytes]
+        .iter()
+        .rposition(|&b| b == b'\n')
+        .map_or(0, |i| i + 1);
+    text.truncate(end);
+    true
+}
+
+/// The group `r` falls in when grouping by INFO `key`.
+fn group_value<'a>(r: &'a VcfRecord, key: &str) -> &'a str {
+    match r.info_value(key) {
+        None => ABSENT_GROUP,
+        Some("") => FLAG_GROUP,
+        Some(value) => value,
+    }
+}
+
+/// Where exports and reports of `path` are written: next to it, or for a
+/// URL in the current directory under the URL's file name.
+fn output_base(path: &Path) -> PathBuf {
+    if !is_url(path) {
+        return path.to_path_buf();
+    }
+    let url = path.to_string_lossy();
+    let name = url
+        .split(['?', '#'])
+        .next()
+        .and_then(|u| u.rsplit('/').next())
+        .filter(|name| !name.is_empty())
+        .unwrap_or("download");
+    PathBuf::from(name)
+}
+
+/// How long rows that a reload added or changed stay highlighted.
+const CHANGE_HIGHLIGHT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    Line, // hidden by default
+    Chrom,
+    Pos,
+    Id,
+    Ref,
+    Alt,
+    Qual,
+    Filter,
+    Info,
+    Source, // only shown, always first, in the combined view
+}
+
+pub const ALL_COLUMNS: &[Column] = &[
+    Column::Line,
+    Column::Chrom,
+    Column::Pos,
+    Column::Id,
+    Column::Ref,
+    Column::Alt,
+    Column::Qual,
+    Column::Filter,
+    Column::Info,
+];
+
+impl Column {
+    pub fn label(self) -> &'static str {
+        match self {
+            Column::Line => "LINE",
+            Column::Chrom => "CHROM",
+            Column::Pos => "POS",
+            Column::Id => "ID",
+            Column::Ref => "REF",
+            Column::Alt => "ALT",
+            Column::Qual => "QUAL",
+            Column::Filter => "FILTER",
+            Column::Info => "INFO",
+            Column::Source => "SOURCE",
+        }
+    }
+
+    pub fn from_label(s: &str) -> Option<Self> {
+        ALL_COLUMNS
+            .iter()
+            .copied()
+            .find(|c| c.label().eq_ignore_ascii_case(s.trim()))
+    }
+
+    /// Whether the column can hold the VCF missing value `.`.
+    pub fn may_be_missing(self) -> bool {
+        !matches!(
+            self,
+            Column::Line | Column::Chrom | Column::Pos | Column::Source
+        )
+    }
+
+    pub fn width(self) -> Constraint {
+        match self {
+            Column::Line => Constraint::Length(7),
+            Column::Chrom => Constraint::Length(8),
+            Column::Pos => Constraint::Length(11),
+            Column::Id => Constraint::Length(12),
+            Column::Ref | Column::Alt => Constraint::Length(8),
+            Column::Qual => Constraint::Length(7),
+            Column::Filter => Constraint::Length(8),
+            Column::Info => Constraint::Min(10),
+            Column::Source => Constraint::Length(16),
+        }
+    }
+
+    /// Orders two records by this column: POS and QUAL numerically (missing
+    /// QUAL last), everything else as text.
+    fn compare(self, a: &VcfRecord, b: &VcfRecord) -> std::cmp::Ordering {
+        match self {
+            Column::Line => a.line.cmp(&b.line),
+            Column::Chrom => a.chrom.cmp(&b.chrom),
+            Column::Pos => a.pos.cmp(&b.pos),
+            Column::Id => a.id.cmp(&b.id),
+            Column::Ref => a.ref_.cmp(&b.ref_),
+            Column::Alt => a.alt.cmp(&b.alt),
+            Column::Qual => match (a.qual, b.qual) {
+                (Some(x), Some(y)) => x.total_cmp(&y),
+                (Some(_), None) => std::cmp::Ordering::Less,
+                (None, Some(_)) => std::cmp::Ordering::Greater,
+                (None, None) => std::cmp::Ordering::Equal,
+            },
+            Column::Filter => a.filter.cmp(&b.filter),
+            Column::Info => a.info.cmp(&b.info),
+            Column::Source => a.source.cmp(&b.source),
+        }
+    }
+
+    pub fn value(self, r: &VcfRecord, vcf: &VcfState) -> String {
+        match self {
+            Column::Line => r.line.to_string(),
+            Column::Chrom => r.chrom.clone(),
+            Column::Pos => vcf.display_pos(r),
+            Column::Id => r.id.clone(),
+            Column::Ref => r.ref_.clone(),
+            Column::Alt => r
+                .sv_label(&vcf.display_pos(r))
+                .unwrap_or_else(|| r.alt.clone()),
+            Column::Qual => r.qual_text().to_string(),
+            Column::Filter => r.filter.clone(),
+            Column::Info => r.info.clone(),
+            Column::Source => r
+                .source
+                .as_ref()
+                .and_then(|p| p.file_name())
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct TabsState {
+    pub index: usize,
+    pub titles: Vec<String>,
+}
+
+#[derive(Default)]
+pub struct FileListState {
+    pub root: PathBuf,
+    pub items: Vec<PathBuf>,
+    pub selected: Option<usize>,
+    pub filter: String,
+    pub loaded: Option<PathBuf>,         // list entry open in the viewer
+    from_stdin: bool,                    // the single `<stdin>` entry can't be re-read
+    pub list_state: ListState,           // kept across frames so ratatui can scroll the list
+    pub scan: Option<Receiver<PathBuf>>, // files from the background scan; None once it ends
+    open_first: bool,                    // load the first file the scan finds
+    pub previews: HashMap<PathBuf, ChromPreview>, // cached per file once scanned
+    pub preview_scan: Option<PreviewScan>, // at most one runs
+}
+
+/// A chromosome preview being counted on a background thread.
+pub struct PreviewScan {
+    pub path: PathBuf,
+    cancel: Arc<AtomicBool>,
+    done: Receiver<ChromPreview>,
+}
+
+/// Records per chromosome of a file from `chrom_counts`, or why there are
+/// none, for the file tab's preview panel.
+pub type ChromPreview = Result<Vec<(String, usize)>, String>;
+
+#[derive(Default)]
+pub struct VcfState {
+    pub path: Option<PathBuf>,
+    pub header: Vec<String>,
+    pub records: Arc<Vec<VcfRecord>>, // shared with a background export
+    pub warnings: Vec<String>,        // from the last parse
+    pub skipped: Vec<String>,         // blank and comment lines the last parse passed over
+    pub samples: Vec<String>,         // sample columns declared in the header
+    pub info_keys: Vec<String>,       // distinct INFO keys across the records, sorted
+    pub info_numbers: HashMap<String, String>, // `Number` of each `##INFO` header line
+    pub bookmarks: HashSet<BookmarkKey>,
+    pub selected: Option<usize>,
+    pub filters: FilterSet,
+    pub bed_error: Option<String>,
+    pub index: Option<PosIndex>,
+    pub show_dimmed: bool,  // keep non-matching records in the list, greyed out
+    pub zero_based: bool,   // display-only; filters always take 1-based POS
+    pub relative_pos: bool, // list POS as an offset from the top visible row
+    pub show_legend: bool,  // colour key over the variant list
+    pub wrap_selected: bool, // wrap the selected row's REF/ALT over several lines
+    pub plain_rows: bool,   // don't tint rows by variant type
+    pub gt_alleles: bool,   // detail panel spells GT out as REF/ALT alleles
+    pub filter_pane: u16,   // percent of the width taken by the filter column
+    pub head: bool,         // show only the first `head_limit` matches
+    pub head_limit: usize,
+    pub changes: Option<RecordChanges>, // from the last reload, highlighted for a while
+    pub group_by: Option<String>,       // INFO key the list is grouped by
+    pub expanded_groups: HashSet<String>,
+    pub group_selected: Option<usize>, // indexes `App::group_rows`
+    pub group_state: ListState,
+    groups: RefCell<Option<GroupCache>>, // `App::info_groups`, as last built
+    pub visible_columns: Vec<Column>,
+    pub column_offset: usize, // columns scrolled off to the left of the pinned ones
+    pub combined: bool,       // records come from every scanned file, tagged by source
+    has_sv: bool,             // any record is a structural variant; widens ALT
+    pub detail_focused: bool,
+    pub detail_scroll: u16,
+    pub detail_max_scroll: u16, // updated on render from the wrapped line count
+    pub sort_column: Option<Column>, // None keeps file order
+    pub sort_descending: bool,
+    pub header_cells: Vec<(Column, Rect)>, // updated on render for mouse clicks
+    pub table_state: TableState,           // kept across frames so the scroll offset sticks
+}
+
+impl VcfState {
+    pub fn display_pos(&self, r: &VcfRecord) -> String {
+        if self.zero_based {
+            r.pos.saturating_sub(1).to_string()
+        } else {
+            r.pos.to_string()
+        }
+    }
+
+    /// Whether records are coordinate-sorted: each chromosome in one
+    /// contiguous block with POS non-decreasing inside it. The position index
+    /// and chromosome jumps rely on this.
+    pub fn is_sorted(&self) -> bool {
+        self.index.is_some() || self.records.is_empty()
+    }
+
+    /// Column width, widening ALT when it has to fit SV spans.
+    pub fn column_width(&self, column: Column) -> Constraint {
+        if column == Column::Alt && self.has_sv {
+            Constraint::Length(28)
+        } else {
+            column.width()
+        }
+    }
+
+    pub fn coordinate_label(&self) -> &'static str {
+        if self.zero_based {
+            "0-based"
+        } else {
+            "1-based"
+        }
+    }
+
+    /// Columns as laid out in the table.
+    pub fn table_columns(&self) -> Vec<Column> {
+        let mut columns = self.visible_columns.clone();
+        if self.combined {
+            columns.insert(0, Column::Source);
+        }
+        columns
+    }
+
+    /// `table_columns` after horizontal scrolling. The leading key columns
+    /// (SOURCE, LINE, CHROM, POS) stay pinned; `column_offset` of the rest
+    /// are skipped, always leaving at least one.
+    pub fn scrolled_columns(&self) -> Vec<Column> {
+        let mut columns = self.table_columns();
+        let (pinned, max) = Self::scroll_bounds(&columns);
+        let skip = self.column_offset.min(max);
+        columns.drain(pinned..pinned + skip);
+        columns
+    }
+
+    /// Number of pinned columns and the largest useful `column_offset`.
+    fn scroll_bounds(columns: &[Column]) -> (usize, usize) {
+        let pinned = columns
+            .iter()
+            .take_while(|c| {
+                matches!(
+                    c,
+                    Column::Source | Column::Line | Column::Chrom | Column::Pos
+                )
+            })
+            .count();
+        (pinned, (columns.len() - pinned).saturating_sub(1))
+    }
+
+    pub fn scroll_columns(&mut self, right: bool) {
+        let (_, max) = Self::scroll_bounds(&self.table_columns());
+        self.column_offset = if right {
+            (self.column_offset + 1).min(max)
+        } else {
+            self.column_offset.saturating_sub(1)
+        };
+    }
+
+    pub fn toggle_column(&mut self, column: Column) {
+        if self.visible_columns.contains(&column) {
+            if self.visible_columns.len() > 1 {
+                self.visible_columns.retain(|&c| c != column);
+            }
+        } else {
+            self.visible_columns.push(column);
+            self.visible_columns
+                .sort_by_key(|c| ALL_COLUMNS.iter().position(|a| a == c).unwrap_or_default());
+        }
+    }
+}
+
+/// Identifies a bookmarked record independently of row order, so bookmarks
+/// survive sorting and filtering.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BookmarkKey {
+    pub chrom: String,
+    pub pos: u64,
+    pub ref_: String,
+    pub alt: String,
+}
+
+impl BookmarkKey {
+    pub fn of(r: &VcfRecord) -> Self {
+        BookmarkKey {
+            chrom: r.chrom.clone(),
+            pos: r.pos,
+            ref_: r.ref_.clone(),
+            alt: r.alt.clone(),
+        }
+    }
+}
+
+/// Group of the records that lack the `group_by` key.
+pub const ABSENT_GROUP: &str = "(absent)";
+/// Group of the records where the `group_by` key is a flag.
+pub const FLAG_GROUP: &str = "(flag)";
+
+/// The grouped list's buckets as record indexes, and the settings they were
+/// built under; `info_groups` rebuilds them when any of those change.
+struct GroupCache {
+    key: String,
+    filters: FilterSet,
+    sort: (Option<Column>, bool),
+    show_dimmed: bool,
+    head: Option<usize>,
+    groups: Vec<Vec<usize>>,
+}
+
+/// A line of the grouped variant list.
+#[derive(Debug, Clone, Copy)]
+pub enum GroupRow<'a> {
+    Group {
+        value: &'a str,
+        count: usize,
+        expanded: bool,
+    },
+    Record(&'a VcfRecord),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordChange {
+    Added,
+    Changed, // same CHROM/POS/REF/ALT, other columns differ
+}
+
+/// What reloading the same file brought in, keyed like bookmarks.
+#[derive(Debug)]
+pub struct RecordChanges {
+    pub records: HashMap<BookmarkKey, RecordChange>,
+    pub removed: usize,
+    pub at: Instant,
+}
+
+impl RecordChanges {
+    fn between(old: &[VcfRecord], new: &[VcfRecord]) -> Self {
+        let columns = |r: &VcfRecord| {
+            (
+                r.id.clone(),
+                r.qual_text().to_string(),
+                r.filter.clone(),
+                r.info.clone(),
+            )
+        };
+        let before: HashMap<BookmarkKey, _> = old
+            .iter()
+            .map(|r| (BookmarkKey::of(r), columns(r)))
+            .collect();
+        let mut records = HashMap::new();
+        let mut kept = 0;
+        for r in new {
+            let key = BookmarkKey::of(r);
+            match before.get(&key) {
+                None => {
+                    records.insert(key, RecordChange::Added);
+                }
+                Some(old) => {
+                    kept += 1;
+                    if *old != columns(r) {
+                        records.insert(key, RecordChange::Changed);
+                    }
+                }
+            }
+        }
+        RecordChanges {
+            records,
+            removed: before.len().saturating_sub(kept),
+            at: Instant::now(),
+        }
+    }
+
+    fn count(&self, change: RecordChange) -> usize {
+        self.records.values().filter(|&&c| c == change).count()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ModalKind {
+    #[default]
+    Menu,
+    Chrom,
+    Ref,
+    Alt,
+    Pos,
+    Id,
+    IndelLen,
+    SvType,
+    Info,
+    Flag,
+    Bed,
+    Columns,
+    ConfirmQuit,
+    Export,
+    Command,
+    Recent,
+    Region,
+    GroupBy,
+    Record, // every field of the selected record, untruncated
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuItem {
+    Chrom,
+    Ref,
+    Alt,
+    Pos,
+    Region,
+    Id,
+    NovelOnly,
+    KnownOnly,
+    IndelLen,
+    SvType,
+    Info,
+    Flag,
+    MultiAllelic,
+    NonMissing,
+    Bed,
+    ClearAll,
+    Cancel,
+}
+
+pub const MENU_ITEMS: &[MenuItem] = &[
+    MenuItem::Chrom,
+    MenuItem::Ref,
+    MenuItem::Alt,
+    MenuItem::Pos,
+    MenuItem::Region,
+    MenuItem::Id,
+    MenuItem::NovelOnly,
+    MenuItem::KnownOnly,
+    MenuItem::IndelLen,
+    MenuItem::SvType,
+    MenuItem::Info,
+    MenuItem::Flag,
+    MenuItem::MultiAllelic,
+    MenuItem::NonMissing,
+    MenuItem::Bed,
+    MenuItem::ClearAll,
+    MenuItem::Cancel,
+];
+
+impl MenuItem {
+    pub fn label(self) -> &'static str {
+        match self {
+            MenuItem::Chrom => "CHROM",
+            MenuItem::Ref => "REF",
+            MenuItem::Alt => "ALT",
+            MenuItem::Pos => "POS",
+            MenuItem::Region => "Region (chr:start-end)",
+            MenuItem::Id => "ID",
+            MenuItem::NovelOnly => "Novel only",
+            MenuItem::KnownOnly => "Known only",
+            MenuItem::IndelLen => "INDEL size",
+            MenuItem::SvType => "SVTYPE",
+            MenuItem::Info => "INFO",
+            MenuItem::Flag => "INFO flag",
+            MenuItem::MultiAllelic => "Multi-allelic only",
+            MenuItem::NonMissing => "Non-missing columns",
+            MenuItem::Bed => "BED regions",
+            MenuItem::ClearAll => "Clear all",
+            MenuItem::Cancel => "Cancel",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportItem {
+    Filtered,
+    Bookmarked,
+    Bed,
+    Cancel,
+}
+
+pub const EXPORT_ITEMS: &[ExportItem] = &[
+    ExportItem::Filtered,
+    ExportItem::Bookmarked,
+    ExportItem::Bed,
+    ExportItem::Cancel,
+];
+
+impl ExportItem {
+    pub fn label(self) -> &'static str {
+        match self {
+            ExportItem::Filtered => "Filtered records",
+            ExportItem::Bookmarked => "Bookmarked only",
+            ExportItem::Bed => "Filtered positions as BED",
+            ExportItem::Cancel => "Cancel",
+        }
+    }
+}
+
+/// An export being written on a background thread, to `out` with a `.part`
+/// suffix until it completes.
+pub struct ExportJob {
+    pub out: PathBuf,
+    pub total: usize,
+    pub written: Arc<AtomicUsize>, // records written so far
+    cancel: Arc<AtomicBool>,
+    done: Receiver<io::Result<()>>,
+}
+
+/// Passes writes through to `inner`, counting the records (lines after the
+/// first `header_lines`) and failing once `cancel` is set.
+struct ExportWriter<W> {
+    inner: W,
+    header_lines: usize,
+    lines: usize,
+    written: Arc<AtomicUsize>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl<W: Write> Write for ExportWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.cancel.load(Ordering::Relaxed) {
+            return Err(io::Error::other("cancelled"));
+        }
+        let n = self.inner.write(buf)?;
+        self.lines += buf[..n].iter().filter(|&&b| b == b'\n').count();
+        self.written.store(
+            self.lines.saturating_sub(self.header_lines),
+            Ordering::Relaxed,
+        );
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[derive(Default)]
+pub struct ModalState {
+    pub kind: ModalKind,
+    pub input: String,
+    pub cursor: usize, // characters of `input` before the text cursor
+    pub menu_selected: usize,
+    pub history_pos: Option<usize>, // index into the kind's history while recalling
+    pub match_count: Option<usize>, // records that would pass with `input` applied
+    pub count_due: Option<Instant>, // when to recompute `match_count`
+    pub scroll: u16,                // lines scrolled in the full-record view
+    pub max_scroll: u16,            // updated on render
+}
+
+impl ModalState {
+    pub fn new_menu() -> Self {
+        Self {
+            kind: ModalKind::Menu,
+            count_due: None,
+            ..Self::default()
+        }
+    }
+    pub fn new_input(kind: ModalKind) -> Self {
+        Self {
+            kind,
+            count_due: Some(Instant::now()),
+            ..Self::default()
+        }
+    }
+
+    /// Byte offset of the text cursor in `input`, at most its end.
+    pub fn cursor_offset(&self) -> usize {
+        self.input
+            .char_indices()
+            .nth(self.cursor)
+            .map_or(self.input.len(), |(i, _)| i)
+    }
+
+    /// Moves the cursor to `cursor` characters in, clamped to the text.
+    pub fn move_cursor(&mut self, cursor: usize) {
+        self.cursor = cursor.min(self.input.chars().count());
+    }
+
+    pub fn insert(&mut self, c: char) {
+        let at = self.cursor_offset();
+        self.input.insert(at, c);
+        self.move_cursor(self.cursor + 1);
+    }
+
+    /// Deletes the character before the cursor, or with `forward` the one
+    /// under it.
+    pub fn delete(&mut self, forward: bool) {
+        self.move_cursor(self.cursor);
+        if !forward {
+            if self.cursor == 0 {
+                return;
+            }
+            self.cursor -= 1;
+        }
+        let at = self.cursor_offset();
+        if at < self.input.len() {
+            self.input.remove(at);
+        }
+    }
+
+    /// Deletes back from the cursor to the start of the word before it,
+    /// like readline's Ctrl+W; words are separated by whitespace.
+    pub fn delete_word(&mut self) {
+        self.move_cursor(self.cursor);
+        let end = self.cursor_offset();
+        let head = self.input[..end].trim_end();
+        let start = head
+            .char_indices()
+            .rev()
+            .find(|(_, c)| c.is_whitespace())
+            .map_or(0, |(i, c)| i + c.len_utf8());
+        self.cursor -= self.input[start..end].chars().count();
+        self.input.replace_range(start..end, "");
+    }
+
+    /// Replaces the text, leaving the cursor at its end.
+    pub fn set_input(&mut self, text: String) {
+        self.cursor = text.chars().count();
+        self.input = text;
+    }
+}
+
+impl App {
+    pub fn new() -> Self {
+        let mut app = App::default();
+        app.tabs.titles = vec![
+            "Files".to_owned(),
+            "VCF Viewer".to_owned(),
+            "Stats".to_owned(),
+        ];
+        app.config = Config::load();
+        app.keymap = KeyMap::from_config(&app.config);
+        app.comment_prefixes = app.config.comment_prefixes();
+        app.wrap_navigation = app.config.get("wrap_navigation") == Some("true");
+        app.percent_decode = app.config.get("percent_decode") == Some("true");
+        // https://no-color.org: any non-empty value disables colour.
+        app.no_color = env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty())
+            || env::var("TERM").is_ok_and(|t| t == "dumb");
+        app.context_rows = app
+            .config
+            .get("context_rows")
+            .and_then(|v| v.trim().parse().ok())
+            .unwrap_or(3);
+        if let Some((column, descending)) = app.config.get("sort").and_then(parse_sort) {
+            app.vcf.sort_column = Some(column);
+            app.vcf.sort_descending = descending;
+        }
+        app.vcf.filter_pane = app
+            .config
+            .get("filter_pane")
+            .and_then(|v| v.trim().parse().ok())
+            .unwrap_or(FILTER_PANE)
+            .clamp(FILTER_PANE_BOUNDS.0, FILTER_PANE_BOUNDS.1);
+        app.vcf.head_limit = app
+            .config
+            .get("head_limit")
+            .and_then(|v| v.trim().parse().ok())
+            .filter(|&n| n > 0)
+            .unwrap_or(HEAD_LIMIT);
+        app.copy_limit = app
+            .config
+            .get("copy_limit")
+            .and_then(|v| v.trim().parse().ok())
+            .unwrap_or(COPY_LIMIT);
+        app.vcf.visible_columns = app
+            .config
+            .get("columns")
+            .map(|v| v.split(',').filter_map(Column::from_label).collect())
+            .filter(|cols: &Vec<Column>| !cols.is_empty())
+            .unwrap_or_else(|| {
+                ALL_COLUMNS
+                    .iter()
+                    .copied()
+                    .filter(|&c| c != Column::Line)
+                    .collect()
+            });
+        // Files deleted since are dropped; the next save forgets them.
+        app.recent = app
+            .config
+            .recent_path()
+            .and_then(|p| fs::read_to_string(p).ok())
+            .map(|s| {
+                s.lines()
+                    .map(PathBuf::from)
+                    .filter(|p| is_url(p) || p.is_file())
+                    .collect()
+            })
+            .unwrap_or_default();
+        app.recent.truncate(RECENT_LIMIT);
+        app.scan_vcf_files();
+        app
+    }
+
+    /// Moves `path` to the top of the recent files and saves the list.
+    fn remember_recent(&mut self, path: &Path) {
+        self.recent.retain(|p| p != path);
+        self.recent.insert(0, path.to_path_buf());
+        self.recent.truncate(RECENT_LIMIT);
+        if let Some(out) = self.config.recent_path() {
+            let mut contents = String::new();
+            for p in &self.recent {
+                contents.push_str(&format!("{}\n", p.display()));
+            }
+            let _ = fs::create_dir_all(out.parent().unwrap_or(Path::new(".")))
+                .and_then(|()| fs::write(out, contents));
+        }
+    }
+
+    /// Opens a recent file, reusing its file list entry when it was
+    /// scanned and adding it otherwise (it may be outside the scan root).
+    pub fn open_recent(&mut self, index: usize) {
+        let Some(path) = self.recent.get(index).cloned() else {
+            return;
+        };
+        let entry = self
+            .files
+            .items
+            .iter()
+            .find(|p| fs::canonicalize(p).is_ok_and(|p| p == path))
+            .cloned()
+            .unwrap_or(path);
+        self.select_path(entry);
+        self.pending_load = true;
+        self.tabs.index = 1;
+    }
+
+    /// Walks the current directory on a background thread; `poll_scan`
+    /// picks up what it finds so a deep tree doesn't hold up startup.
+    fn scan_vcf_files(&mut self) {
+        self.files.root = PathBuf::from(".");
+        let root = self.files.root.clone();
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            for entry in WalkDir::new(root).into_iter().filter_map(|e| e.ok()) {
+                let path = entry.path();
+                if path
+                    .extension()
+                    .is_some_and(|ext| ext == "vcf" || ext == "bcf")
+                    && tx.send(path.to_owned()).is_err()
+                {
+                    return;
+                }
+            }
+        });
+        self.files.scan = Some(rx);
+    }
+
+    /// Opens the first file once the scan finds one, as startup without a
+    /// path used to do.
+    pub fn open_first_scanned(&mut self) {
+        self.files.open_first = true;
+        self.poll_scan();
+    }
+
+    /// Moves newly scanned files into the list, keeping the selected file
+    /// selected. Returns whether anything changed.
+    pub fn poll_scan(&mut self) -> bool {
+        let Some(scan) = &self.files.scan else {
+            return false;
+        };
+        let selected = self.selected_file().cloned();
+        let mut found = Vec::new();
+        let done = loop {
+            match scan.try_recv() {
+                Ok(path) => found.push(path),
+                Err(TryRecvError::Empty) => break false,
+                Err(TryRecvError::Disconnected) => break true,
+            }
+        };
+        let changed = done || !found.is_empty();
+        if !found.is_empty() {
+            for path in found {
+                if !self.files.items.contains(&path) {
+                    self.files.items.push(path);
+                }
+            }
+            let visible = self.visible_files();
+            self.files.selected = match selected {
+                Some(path) => visible.iter().position(|p| **p == path),
+                None => (!visible.is_empty()).then_some(0),
+            };
+            if self.files.open_first && self.files.selected.is_some() {
+                self.files.open_first = false;
+                self.pending_load = true;
+            }
+        }
+        if done {
+            self.files.scan = None;
+            self.files.open_first = false;
+        }
+        changed
+    }
+
+    /// Picks up a finished chromosome preview and, on the file tab, starts
+    /// one for the highlighted file if it has none yet. One scan runs at a
+    /// time: moving to another file cancels a scan still running for the
+    /// last one, so scrolling quickly through the list doesn't pile them up.
+    /// Returns whether a preview arrived.
+    pub fn poll_preview(&mut self) -> bool {
+        let mut arrived = false;
+        if let Some(scan) = &self.files.preview_scan {
+            let result = match scan.done.try_recv() {
+                Ok(result) => Some(result),
+                Err(TryRecvError::Empty) => None,
+                Err(TryRecvError::Disconnected) => Some(Err("scan stopped".to_string())),
+            };
+            if let Some(result) = result {
+                self.files.previews.insert(scan.path.clone(), result);
+                self.files.preview_scan = None;
+                arrived = true;
+            }
+        }
+        if self.tabs.index != 0 || self.files.from_stdin {
+            return arrived;
+        }
+        let Some(path) = self.selected_file().cloned() else {
+            return arrived;
+        };
+        match &self.files.preview_scan {
+            Some(scan) if scan.path == path => return arrived,
+            Some(scan) => {
+                // Dropping the receiver abandons the stale result.
+                scan.cancel.store(true, Ordering::Relaxed);
+                self.files.preview_scan = None;
+            }
+            None => {}
+        }
+        if self.files.previews.contains_key(&path) {
+            return arrived;
+        }
+        if is_url(&path) {
+            let result = Err("no preview for URLs".to_string());
+            self.files.previews.insert(path, result);
+            return true;
+        }
+        let (tx, done) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let (scanned, cancelled) = (path.clone(), Arc::clone(&cancel));
+        let (delimiter, comments) = (self.delimiter, self.comment_prefixes.clone());
+        thread::spawn(move || {
+            let counts = chrom_counts(&scanned, delimiter, &comments, &cancelled);
+            let _ = tx.send(counts.map_err(|e| e.to_string()));
+        });
+        self.files.preview_scan = Some(PreviewScan { path, cancel, done });
+        arrived
+    }
+
+    /// Files matching the file-tab filter, best fuzzy match first;
+    /// `files.selected` indexes this list.
+    pub fn visible_files(&self) -> Vec<&PathBuf> {
+        if self.files.filter.is_empty() {
+            return self.files.items.iter().collect();
+        }
+        let mut scored: Vec<(i64, &PathBuf)> = self
+            .files
+            .items
+            .iter()
+            .filter_map(|p| Some((self.file_match(p)?.0, p)))
+            .collect();
+        scored.sort_by_key(|&(score, _)| Reverse(score));
+        scored.into_iter().map(|(_, p)| p).collect()
+    }
+
+    /// Fuzzy-matches the file-tab filter against `path` (fzf style, so
+    /// `s1exm` finds `sample1.exome.vcf`), giving the score and the char
+    /// positions of the matched characters.
+    pub fn file_match(&self, path: &Path) -> Option<(i64, Vec<usize>)> {
+        SkimMatcherV2::default()
+            .ignore_case()
+            .fuzzy_indices(&path.to_string_lossy(), &self.files.filter)
+    }
+
+    pub fn selected_file(&self) -> Option<&PathBuf> {
+        self.files
+            .selected
+            .and_then(|i| self.visible_files().get(i).copied())
+    }
+
+    /// If the file filter is a path to an existing VCF/BCF or a URL, adds it
+    /// to the list (it may lie outside the scan root) and selects it.
+    pub fn select_typed_path(&mut self) -> bool {
+        let path = PathBuf::from(self.files.filter.trim());
+        let is_vcf = path
+            .extension()
+            .is_some_and(|ext| ext == "vcf" || ext == "bcf");
+        if !is_url(&path) && (!is_vcf || !path.is_file()) {
+            return false;
+        }
+        self.select_path(path);
+        true
+    }
+
+    /// Adds `path` to the file list if missing and selects it.
+    pub fn select_path(&mut self, path: PathBuf) {
+        if !self.files.items.contains(&path) {
+            self.files.items.push(path.clone());
+        }
+        self.files.filter.clear();
+        self.files.selected = self.visible_files().iter().position(|p| **p == path);
+    }
+
+    pub fn set_file_filter(&mut self, filter: String) {
+        self.files.filter = filter;
+        self.files.list_state = ListState::default();
+        self.files.selected = (!self.visible_files().is_empty()).then_some(0);
+    }
+
+    /// Replaces the file list with a single `<stdin>` entry holding the
+    /// records read from `input`, and opens the viewer on it.
+    pub fn load_stdin(&mut self, mut input: impl Read) -> Result<(), Box<dyn std::error::Error>> {
+        let mut data = Vec::new();
+        input.read_to_end(&mut data)?;
+        let path = PathBuf::from("<stdin>");
+        self.files.items = vec![path.clone()];
+        self.files.loaded = Some(path.clone());
+        self.files.filter.clear();
+        self.files.selected = Some(0);
+        self.files.from_stdin = true;
+        self.files.scan = None;
+        let started = Instant::now();
+        self.set_records(parse_vcf_bytes(
+            &data,
+            self.delimiter,
+            &self.comment_prefixes,
+        )?);
+        self.notify(format!(
+            "Loaded {} records from stdin in {} ms{}",
+            self.vcf.records.len(),
+            started.elapsed().as_millis(),
+            self.warning_suffix()
+        ));
+        self.vcf.path = Some(path);
+        self.tabs.index = 1;
+        Ok(())
+    }
+
+    fn set_records(&mut self, parsed: ParsedVcf) {
+        if let Some(path) = &self.vcf.path
+            && !self.vcf.combined
+        {
+            let view = (self.vcf.selected, self.vcf.table_state.offset());
+            self.views.insert(path.clone(), view);
+        }
+        self.vcf.table_state = TableState::default();
+        self.vcf.info_numbers = info_numbers(&parsed.header);
+        self.vcf.header = parsed.header;
+        self.vcf.records = Arc::new(parsed.records);
+        self.vcf.warnings = parsed.warnings;
+        self.vcf.skipped = parsed.skipped;
+        self.vcf.samples = parsed.samples;
+        self.vcf.info_keys = self
+            .vcf
+            .records
+            .iter()
+            .flat_map(|r| parse_info(&r.info).into_iter().map(|(k, _)| k))
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .map(String::from)
+            .collect();
+        self.vcf.index = PosIndex::build(&self.vcf.records);
+        self.vcf.has_sv = self.vcf.records.iter().any(|r| r.sv_type().is_some());
+        self.vcf.selected = None;
+        self.vcf.bookmarks.clear();
+        self.vcf.changes = None;
+        self.vcf.expanded_groups.clear();
+        self.vcf.group_selected = None;
+        self.vcf.group_state = ListState::default();
+        self.vcf.groups.take();
+    }
+
+    fn warning_suffix(&self) -> String {
+        let mut suffix = match self.vcf.warnings.len() {
+            0 => String::new(),
+            n => format!(", {} warnings (see Stats)", n),
+        };
+        if !self.vcf.is_sorted() {
+            suffix.push_str(&format!(
+                "; file is not coordinate-sorted, {} sorts it in memory",
+                self.keymap.hint(Action::SortRecords)
+            ));
+        }
+        suffix
+    }
+
+    /// Reorders the loaded records by chromosome (in order of first
+    /// appearance) and POS, so the position index and chromosome jumps work
+    /// on files that weren't sorted on disk.
+    pub fn sort_records(&mut self) {
+        if self.vcf.is_sorted() {
+            self.notify("Records are already coordinate-sorted".to_string());
+            return;
+        }
+        let mut rank: HashMap<String, usize> = HashMap::new();
+        for r in self.vcf.records.iter() {
+            let next = rank.len();
+            rank.entry(r.chrom.clone()).or_insert(next);
+        }
+        Arc::make_mut(&mut self.vcf.records).sort_by_key(|r| (rank[&r.chrom], r.pos));
+        self.vcf.index = PosIndex::build(&self.vcf.records);
+        self.vcf.groups.take();
+        self.vcf.selected = None;
+        *self.vcf.table_state.offset_mut() = 0;
+        self.notify(format!(
+            "Sorted {} records by position",
+            self.vcf.records.len()
+        ));
+    }
+
+    pub fn load_selected_vcf(&mut self, progress: Option<&mut dyn FnMut(u64, u64)>) {
+        if self.files.from_stdin {
+            return;
+        }
+        let Some(path) = self.selected_file().cloned() else {
+            return;
+        };
+        let previous = (!self.vcf.combined)
+            .then(|| self.vcf.path.clone())
+            .flatten();
+        let started = Instant::now();
+        let parsed = parse_vcf(&path, self.delimiter, &self.comment_prefixes, progress);
+        let elapsed = started.elapsed();
+        match parsed {
+            Ok(parsed) => {
+                let canonical = fs::canonicalize(&path).unwrap_or(path.clone());
+                let changes = (previous.as_ref() == Some(&canonical))
+                    .then(|| RecordChanges::between(&self.vcf.records, &parsed.records));
+                self.set_records(parsed);
+                let text = match &changes {
+                    Some(changes) => format!(
+                        "Reloaded {} records: {} new, {} changed, {} removed{}",
+                        self.vcf.records.len(),
+                        changes.count(RecordChange::Added),
+                        changes.count(RecordChange::Changed),
+                        changes.removed,
+                        self.warning_suffix()
+                    ),
+                    None => format!(
+                        "Loaded {} records in {} ms{}",
+                        self.vcf.records.len(),
+                        elapsed.as_millis(),
+                        self.warning_suffix()
+                    ),
+                };
+                self.notify(text);
+                self.vcf.changes = changes;
+                self.remember_recent(&fs::canonicalize(&path).unwrap_or(path.clone()));
+            }
+            Err(e) => {
+                self.set_records(ParsedVcf::default());
+                self.notify(format!("Failed to load {}: {}", path.display(), e));
+            }
+        }
+        self.vcf.combined = false;
+        self.files.loaded = Some(path.clone());
+        let path = fs::canonicalize(&path).unwrap_or(path);
+        // Switching files swaps filter sets; the first file opened keeps
+        // whatever was set up front (e.g. on the command line).
+        if let Some(previous) = previous
+            && previous != path
+        {
+            let remembered = self.file_filters.remove(&path).unwrap_or_default();
+            let left = std::mem::replace(&mut self.vcf.filters, remembered);
+            self.vcf.filters.case_sensitive = left.case_sensitive;
+            self.vcf.bed_error = None;
+            self.file_filters.insert(previous, left);
+        }
+        if let Some(&(selected, offset)) = self.views.get(&path) {
+            let len = self.display_rows().len();
+            self.vcf.selected = selected.filter(|&i| i < len);
+            *self.vcf.table_state.offset_mut() = offset.min(len.saturating_sub(1));
+        }
+        self.vcf.path = Some(path);
+    }
+
+    /// Loads every scanned file into one list, tagging each record with its
+    /// source, so the filters run across the whole cohort at once.
+    pub fn load_all_files(&mut self) {
+        if self.files.from_stdin {
+            return;
+        }
+        self.files.loaded = None;
+        let mut combined = ParsedVcf::default();
+        for path in &self.files.items {
+            let source: Arc<Path> = Arc::from(path.as_path());
+            let parsed = match parse_vcf(path, self.delimiter, &self.comment_prefixes, None) {
+                Ok(parsed) => parsed,
+                Err(e) => {
+                    combined.warnings.push(format!("{}: {}", path.display(), e));
+                    continue;
+                }
+            };
+            if combined.header.is_empty() {
+                combined.header = parsed.header;
+            }
+            combined
+                .records
+                .extend(parsed.records.into_iter().map(|r| VcfRecord {
+                    source: Some(source.clone()),
+                    ..r
+                }));
+            combined.warnings.extend(
+                parsed
+                    .warnings
+                    .into_iter()
+                    .map(|w| format!("{}: {}", path.display(), w)),
+            );
+            combined.skipped.extend(
+                parsed
+                    .skipped
+                    .into_iter()
+                    .map(|s| format!("{}: {}", path.display(), s)),
+            );
+        }
+        self.set_records(combined);
+        self.vcf.combined = true;
+        self.vcf.path = Some(fs::canonicalize(&self.files.root).unwrap_or(self.files.root.clone()));
+        self.tabs.index = 1;
+    }
+
+    /// State that quitting would throw away.
+    fn has_unsaved_state(&self) -> bool {
+        !self.active_filters().is_empty() || !self.vcf.bookmarks.is_empty()
+    }
+
+    pub fn toggle_bookmark(&mut self) {
+        let Some(r) = self.selected_record() else {
+            return;
+        };
+        let key = BookmarkKey::of(r);
+        if !self.vcf.bookmarks.remove(&key) {
+            self.vcf.bookmarks.insert(key);
+        }
+    }
+
+    pub fn is_bookmarked(&self, r: &VcfRecord) -> bool {
+        self.vcf.bookmarks.contains(&BookmarkKey::of(r))
+    }
+
+    /// Indexes into `vcf.records` of the bookmarked records, in genomic
+    /// order: chromosomes as they first appear in the file, then by POS.
+    fn bookmarked_indexes(&self) -> Vec<usize> {
+        let records = &self.vcf.records;
+        let mut chrom_rank: HashMap<&str, usize> = HashMap::new();
+        for r in records.iter() {
+            let next = chrom_rank.len();
+            chrom_rank.entry(&r.chrom).or_insert(next);
+        }
+        let mut indexes: Vec<usize> = (0..records.len())
+            .filter(|&i| self.is_bookmarked(&records[i]))
+            .collect();
+        indexes.sort_by_key(|&i| (chrom_rank[records[i].chrom.as_str()], records[i].pos));
+        indexes
+    }
+
+    /// Writes the chosen set next to the loaded file, e.g.
+    /// `sample.vcf.bookmarked.vcf` or `sample.vcf.filtered.bed`.
+    pub fn export(&mut self, item: ExportItem) {
+        let Some(path) = &self.vcf.path else {
+            self.notify("No VCF loaded".to_string());
+            return;
+        };
+        let name = match item {
+            ExportItem::Filtered => "filtered.vcf",
+            ExportItem::Bookmarked => "bookmarked.vcf",
+            ExportItem::Bed => "filtered.bed",
+            ExportItem::Cancel => return,
+        };
+        let out = PathBuf::from(format!("{}.{}", output_base(path).display(), name));
+        self.export_to(item, &out);
+    }
+
+    /// Starts writing the chosen set to `out` in the background: as VCF
+    /// under the loaded file's header, sample columns included, or as BED.
+    /// `poll_export` reports the result.
+    pub fn export_to(&mut self, item: ExportItem, out: &Path) {
+        if self.vcf.path.is_none() {
+            self.notify("No VCF loaded".to_string());
+            return;
+        }
+        if let Some(job) = &self.export {
+            self.notify(format!("Still exporting to {}", job.out.display()));
+            return;
+        }
+        // The thread shares the loaded records and picks its own out by index.
+        let (indexes, suffix) = match item {
+            ExportItem::Filtered | ExportItem::Bed => (self.filtered_indexes(), "filtered"),
+            ExportItem::Bookmarked => (self.bookmarked_indexes(), "bookmarked"),
+            ExportItem::Cancel => return,
+        };
+        if indexes.is_empty() {
+            self.notify(format!("Nothing to export ({} set is empty)", suffix));
+            return;
+        }
+        let bed = item == ExportItem::Bed;
+        let header = if bed {
+            Vec::new()
+        } else {
+            self.vcf.header.clone()
+        };
+        let header_lines =
+            header.len() + usize::from(!bed && !header.iter().any(|l| l.starts_with("#CHROM")));
+        let all = Arc::clone(&self.vcf.records);
+        let total = indexes.len();
+        let written = Arc::new(AtomicUsize::new(0));
+        let cancel = Arc::new(AtomicBool::new(false));
+        let (tx, done) = mpsc::channel();
+        let part = PathBuf::from(format!("{}.part", out.display()));
+        let target = out.to_path_buf();
+        let (counter, cancelled) = (Arc::clone(&written), Arc::clone(&cancel));
+        thread::spawn(move || {
+            let records: Vec<&VcfRecord> = indexes.iter().map(|&i| &all[i]).collect();
+            let result = fs::File::create(&part).and_then(|file| {
+                let out = ExportWriter {
+                    inner: io::BufWriter::new(file),
+                    header_lines,
+                    lines: 0,
+                    written: counter,
+                    cancel: cancelled,
+                };
+                if bed {
+                    write_bed(out, &records)
+                } else {
+                    write_vcf(out, &header, &records)
+                }
+            });
+            let result = result.and_then(|()| fs::rename(&part, &target));
+            if result.is_err() {
+                let _ = fs::remove_file(&part);
+            }
+            let _ = tx.send(result);
+        });
+        self.export = Some(ExportJob {
+            out: out.to_path_buf(),
+            total,
+            written,
+            cancel,
+            done,
+        });
+    }
+
+    /// Picks up the result of a background export once it finishes;
+    /// returns whether it did.
+    pub fn poll_export(&mut self) -> bool {
+        let Some(job) = &self.export else {
+            return false;
+        };
+        let result = match job.done.try_recv() {
+            Ok(result) => result,
+            Err(TryRecvError::Empty) => return false,
+            Err(TryRecvError::Disconnected) => Err(io::Error::other("export thread stopped")),
+        };
+        let text = match result {
+            Ok(()) => format!("Exported {} records to {}", job.total, job.out.display()),
+            Err(_) if job.cancel.load(Ordering::Relaxed) => {
+                format!("Export to {} cancelled", job.out.display())
+            }
+            Err(e) => format!("Export failed: {}", e),
+        };
+        self.export = None;
+        self.notify(text);
+        true
+    }
+
+    /// Stops a running export; its partial file is removed.
+    pub fn cancel_export(&mut self) {
+        if let Some(job) = &self.export {
+            job.cancel.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Writes the filtered records to `out` as BED intervals covering REF
+    /// (0-based, half-open), with the ID as a fourth column when any record
+    /// has one. Runs in the background like any export.
+    pub fn export_bed(&mut self, out: &Path) {
+        self.export_to(ExportItem::Bed, out);
+    }
+
+    /// Exits, first cancelling a running export so its `.part` file is not
+    /// left behind.
+    pub fn quit(&mut self) -> ! {
+        if let Some(job) = self.export.take() {
+            job.cancel.store(true, Ordering::Relaxed);
+            // The thread removes the file itself once it sees the flag.
+            if job.done.recv_timeout(Duration::from_secs(1)).is_err() {
+                let _ = fs::remove_file(format!("{}.part", job.out.display()));
+            }
+        }
+        std::process::exit(0);
+    }
+
+    /// Queues the file behind the selected record (or the loaded file) for
+    /// `$EDITOR`, at the record's line. The main loop suspends the TUI
+    /// while the editor runs.
+    pub fn open_in_editor(&mut self) {
+        let record = self.selected_record();
+        let line = record.map(|r| r.line);
+        let path = record
+            .and_then(|r| r.source.as_deref().map(Path::to_path_buf))
+            .or_else(|| self.vcf.path.clone())
+            .filter(|p| !self.files.from_stdin && !is_url(p));
+        match path {
+            Some(path) => self.pending_editor = Some((path, line)),
+            None => self.notify("No local file to open in an editor".to_string()),
+        }
+    }
+
+    /// Exits, unless there is unsaved state to confirm first. Set
+    /// `confirm_quit = false` in the config to always exit straight away.
+    pub fn request_quit(&mut self) {
+        if self.config.get("confirm_quit") != Some("false") && self.has_unsaved_state() {
+            self.modal = Some(ModalState::new_input(ModalKind::ConfirmQuit));
+        } else {
+            self.quit();
+        }
+    }
+
+    pub fn next_tab(&mut self) {
+        self.tabs.index = (self.tabs.index + 1) % self.tabs.titles.len();
+    }
+
+    pub fn prev_tab(&mut self) {
+        let n = self.tabs.titles.len();
+        self.tabs.index = (self.tabs.index + n - 1) % n;
+    }
+
+    pub fn toggle_column(&mut self, column: Column) {
+        self.vcf.toggle_column(column);
+        let labels: Vec<&str> = self.vcf.visible_columns.iter().map(|c| c.label()).collect();
+        self.config.set("columns", &labels.join(","));
+        let _ = self.config.save();
+    }
+
+    /// Widens or narrows the filter column by one step and saves the new
+    /// split to the config.
+    pub fn resize_filter_pane(&mut self, wider: bool) {
+        let (min, max) = FILTER_PANE_BOUNDS;
+        let pane = if wider {
+            self.vcf.filter_pane + FILTER_PANE_STEP
+        } else {
+            self.vcf.filter_pane.saturating_sub(FILTER_PANE_STEP)
+        };
+        self.vcf.filter_pane = pane.clamp(min, max);
+        self.config
+            .set("filter_pane", &self.vcf.filter_pane.to_string());
+        let _ = self.config.save();
+    }
+
+    /// Non-empty filters as (name, value) pairs, in menu order.
+    pub fn active_filters(&self) -> Vec<(&'static str, String)> {
+        self.vcf.filters.active()
+    }
+
+    /// Current value of an active filter, for display in the filter menu.
+    pub fn filter_value(&self, item: MenuItem) -> Option<String> {
+        self.vcf.filters.value(item)
+    }
+
+    pub fn clear_filter(&mut self, item: MenuItem) {
+        match item {
+            MenuItem::Chrom => self.vcf.filters.chrom.clear(),
+            MenuItem::Ref => self.vcf.filters.ref_.clear(),
+            MenuItem::Alt => self.vcf.filters.alt.clear(),
+            MenuItem::Pos => self.vcf.filters.pos.clear(),
+            MenuItem::Region => {
+                self.vcf.filters.chrom.clear();
+                self.vcf.filters.pos.clear();
+            }
+            MenuItem::Id => self.vcf.filters.id.clear(),
+            MenuItem::NovelOnly | MenuItem::KnownOnly => {
+                self.vcf.filters.id_presence = IdPresence::Any
+            }
+            MenuItem::IndelLen => self.vcf.filters.indel.clear(),
+            MenuItem::SvType => self.vcf.filters.svtype.clear(),
+            MenuItem::Info => self.vcf.filters.info.clear(),
+            MenuItem::Flag => self.vcf.filters.flag.clear(),
+            MenuItem::MultiAllelic => self.vcf.filters.multiallelic_only = false,
+            MenuItem::NonMissing => self.vcf.filters.non_missing.clear(),
+            MenuItem::Bed => self.vcf.filters.bed_mode = BedMode::Off,
+            MenuItem::ClearAll | MenuItem::Cancel => {}
+        }
+    }
+
+    /// Clears every filter on the current file.
+    pub fn reset_filters(&mut self) {
+        for &item in MENU_ITEMS {
+            self.clear_filter(item);
+        }
+        self.vcf.selected = None;
+    }
+
+    /// Adds or removes `column` from the columns that may not be `.`.
+    /// Columns that are never missing (CHROM, POS, ...) are ignored.
+    pub fn toggle_non_missing(&mut self, column: Column) {
+        if !column.may_be_missing() {
+            return;
+        }
+        let required = &mut self.vcf.filters.non_missing;
+        if required.contains(&column) {
+            required.retain(|&c| c != column);
+        } else {
+            required.push(column);
+            required.sort_by_key(|c| ALL_COLUMNS.iter().position(|a| a == c));
+        }
+        self.clamp_selection();
+    }
+
+    /// Menu toggle for the novel/known shortcuts; picking the active one
+    /// again turns it off.
+    pub fn toggle_id_presence(&mut self, presence: IdPresence) {
+        self.vcf.filters.id_presence = if self.vcf.filters.id_presence == presence {
+            IdPresence::Any
+        } else {
+            presence
+        };
+    }
+
+    pub fn load_bed(&mut self, path: &Path) {
+        match parse_bed(path) {
+            Ok(bed) => {
+                self.vcf.filters.bed = Some(bed);
+                self.vcf.filters.bed_mode = BedMode::Inside;
+                self.vcf.bed_error = None;
+            }
+            Err(e) => {
+                self.vcf.bed_error = Some(format!("{}: {}", path.display(), e));
+            }
+        }
+        self.vcf.selected = None;
+    }
+
+    /// Indexes of the records passing the filters as listed on the VCF tab:
+    /// only the first `head_limit` while the head toggle is on. Exports,
+    /// copies, stats and the report use every match (`filtered_records`).
+    fn listed_indexes(&self) -> Vec<usize> {
+        let mut indexes = self.filtered_indexes();
+        if let Some(cap) = self.head_cap() {
+            indexes.truncate(cap);
+        }
+        indexes
+    }
+
+    /// Records passing the filters, in file order.
+    pub fn filtered_records(&self) -> Vec<&VcfRecord> {
+        self.records_at(self.filtered_indexes())
+    }
+
+    /// `filtered_records` as indexes into `vcf.records`, e.g. for handing
+    /// to a background thread.
+    fn filtered_indexes(&self) -> Vec<usize> {
+        let bounds = match self.vcf.filters.pos_range() {
+            PosRange::None => None,
+            PosRange::Exact(pos) => Some((pos, pos)),
+            PosRange::Range(start, end) => Some((start, end)),
+        };
+        let ranges = match (bounds, &self.vcf.index) {
+            (Some((start, end)), Some(index)) => index.ranges(start, end),
+            _ => std::iter::once(0..self.vcf.records.len()).collect(),
+        };
+        let pos_range = self.vcf.filters.pos_range();
+        ranges
+            .into_iter()
+            .flatten()
+            .filter(|&i| self.vcf.filters.matches(&self.vcf.records[i], &pos_range))
+            .collect()
+    }
+
+    fn records_at(&self, indexes: Vec<usize>) -> Vec<&VcfRecord> {
+        indexes.into_iter().map(|i| &self.vcf.records[i]).collect()
+    }
+
+    /// Records as displayed in the variant list, each paired with whether it
+    /// passes the active filters. Unless `show_dimmed` is set, only matching
+    /// records are returned.
+    pub fn classify_records(&self) -> Vec<(&VcfRecord, bool)> {
+        self.rows_at(self.classify_indexes())
+    }
+
+    /// `classify_records` by index into `vcf.records`.
+    fn classify_indexes(&self) -> Vec<(usize, bool)> {
+        if !self.vcf.show_dimmed {
+            return self
+                .listed_indexes()
+                .into_iter()
+                .map(|i| (i, true))
+                .collect();
+        }
+        let pos_range = self.vcf.filters.pos_range();
+        // Matches past the head cap are dimmed along with the failures.
+        let mut room = self.head_cap().unwrap_or(usize::MAX);
+        self.vcf
+            .records
+            .iter()
+            .enumerate()
+            .map(|(i, r)| {
+                let matched = room > 0 && self.vcf.filters.matches(r, &pos_range);
+                room -= usize::from(matched);
+                (i, matched)
+            })
+            .collect()
+    }
+
+    fn rows_at(&self, rows: Vec<(usize, bool)>) -> Vec<(&VcfRecord, bool)> {
+        rows.into_iter()
+            .map(|(i, matched)| (&self.vcf.records[i], matched))
+            .collect()
+    }
+
+    /// The number of matches shown while the head toggle is on.
+    pub fn head_cap(&self) -> Option<usize> {
+        self.vcf.head.then_some(self.vcf.head_limit)
+    }
+
+    /// `(shown, matching)` while the head cap hides some of the records
+    /// the filters match.
+    pub fn head_truncation(&self) -> Option<(usize, usize)> {
+        let cap = self.head_cap()?;
+        let matching = self.filtered_records().len();
+        (matching > cap).then_some((cap, matching))
+    }
+
+    /// Turns the head cap on or off, or with `limit` sets it and turns it
+    /// on.
+    pub fn set_head(&mut self, limit: Option<usize>) {
+        self.keep_selection(|app| match limit {
+            Some(n) => {
+                app.vcf.head_limit = n.max(1);
+                app.vcf.head = true;
+            }
+            None => app.vcf.head = !app.vcf.head,
+        });
+        self.clamp_selection();
+    }
+
+    /// Rows as displayed: `classify_records` in the active sort order.
+    pub fn display_rows(&self) -> Vec<(&VcfRecord, bool)> {
+        self.rows_at(self.display_indexes())
+    }
+
+    /// `display_rows` by index into `vcf.records`.
+    fn display_indexes(&self) -> Vec<(usize, bool)> {
+        let mut rows = self.classify_indexes();
+        self.sort_rows(&mut rows);
+        rows
+    }
+
+    /// Sorts `rows` (indexes into `vcf.records`) by the active sort column,
+    /// if any.
+    fn sort_rows(&self, rows: &mut [(usize, bool)]) {
+        let records = &self.vcf.records;
+        if let Some(column) = self.vcf.sort_column {
+            rows.sort_by(|&(a, _), &(b, _)| {
+                let ord = column.compare(&records[a], &records[b]);
+                if self.vcf.sort_descending {
+                    ord.reverse()
+                } else {
+                    ord
+                }
+            });
+        }
+    }
+
+    /// Applies `change` while keeping the selected record selected, wherever
+    /// it ends up in the new row order.
+    fn keep_selection(&mut self, change: impl FnOnce(&mut Self)) {
+        let current = self.vcf.selected.and_then(|i| {
+            self.display_rows()
+                .get(i)
+                .map(|(r, _)| *r as *const VcfRecord)
+        });
+        change(self);
+        self.vcf.selected = current.and_then(|ptr| {
+            self.display_rows()
+                .iter()
+                .position(|(r, _)| std::ptr::eq(*r, ptr))
+        });
+    }
+
+    /// Pulls the selection back inside the displayed rows after a filter
+    /// change shrinks them; `None` once nothing is left.
+    pub fn clamp_selection(&mut self) {
+        let len = self.display_rows().len();
+        self.vcf.selected = self
+            .vcf
+            .selected
+            .filter(|_| len > 0)
+            .map(|i| i.min(len - 1));
+        let len = self.group_rows().len();
+        self.vcf.group_selected = self
+            .vcf
+            .group_selected
+            .filter(|_| len > 0)
+            .map(|i| i.min(len - 1));
+    }
+
+    /// The record under the cursor: in the grouped list while grouping is
+    /// on, else in the variant table.
+    pub fn selected_record(&self) -> Option<&VcfRecord> {
+        if self.vcf.group_by.is_some() {
+            return match self
+                .vcf
+                .group_selected
+                .and_then(|i| self.group_rows().get(i).copied())
+            {
+                Some(GroupRow::Record(r)) => Some(r),
+                _ => None,
+            };
+        }
+        let rows = self.display_rows();
+        self.vcf.selected.and_then(|i| rows.get(i)).map(|(r, _)| *r)
+    }
+
+    /// The matching records bucketed by their value of the `group_by` INFO
+    /// key, each in display order. Larger groups come first; records
+    /// without the key go last, as `(absent)`.
+    pub fn info_groups(&self) -> Vec<(&str, Vec<&VcfRecord>)> {
+        let Some(key) = self.vcf.group_by.as_deref() else {
+            return Vec::new();
+        };
+        let mut cache = self.vcf.groups.borrow_mut();
+        let vcf = &self.vcf;
+        let fresh = cache.as_ref().is_some_and(|c| {
+            c.key == key
+                && c.filters == vcf.filters
+                && c.sort == (vcf.sort_column, vcf.sort_descending)
+                && c.show_dimmed == vcf.show_dimmed
+                && c.head == self.head_cap()
+        });
+        if !fresh {
+            *cache = Some(GroupCache {
+                key: key.to_string(),
+                filters: vcf.filters.clone(),
+                sort: (vcf.sort_column, vcf.sort_descending),
+                show_dimmed: vcf.show_dimmed,
+                head: self.head_cap(),
+                groups: self.build_groups(key),
+            });
+        }
+        let groups = cache.as_ref().map_or(&[][..], |c| &c.groups);
+        groups
+            .iter()
+            .map(|indexes| {
+                let records: Vec<&VcfRecord> =
+                    indexes.iter().filter_map(|&i| vcf.records.get(i)).collect();
+                let value = records
+                    .first()
+                    .map_or(ABSENT_GROUP, |r| group_value(r, key));
+                (value, records)
+            })
+            .collect()
+    }
+
+    /// The buckets behind `info_groups`, as indexes into `vcf.records`.
+    fn build_groups(&self, key: &str) -> Vec<Vec<usize>> {
+        let mut groups: Vec<(&str, Vec<usize>)> = Vec::new();
+        let mut index: HashMap<&str, usize> = HashMap::new();
+        for (i, matched) in self.display_indexes() {
+            if !matched {
+                continue;
+            }
+            let value = group_value(&self.vcf.records[i], key);
+            let g = *index.entry(value).or_insert_with(|| {
+                groups.push((value, Vec::new()));
+                groups.len() - 1
+            });
+            groups[g].1.push(i);
+        }
+        groups.sort_by_key(|(value, indexes)| (*value == ABSENT_GROUP, Reverse(indexes.len())));
+        groups.into_iter().map(|(_, indexes)| indexes).collect()
+    }
+
+    /// Lines of the grouped list: each group's header, followed by its
+    /// records when it is expanded.
+    pub fn group_rows(&self) -> Vec<GroupRow<'_>> {
+        let mut rows = Vec::new();
+        for (value, records) in self.info_groups() {
+            let expanded = self.vcf.expanded_groups.contains(value);
+            rows.push(GroupRow::Group {
+                value,
+                count: records.len(),
+                expanded,
+            });
+            if expanded {
+                rows.extend(records.into_iter().map(GroupRow::Record));
+            }
+        }
+        rows
+    }
+
+    /// Groups the list by an INFO key, or goes back to the flat table.
+    pub fn set_group_by(&mut self, key: Option<String>) {
+        self.vcf.group_by = key.filter(|k| !k.is_empty());
+        self.vcf.expanded_groups.clear();
+        self.vcf.group_state = ListState::default();
+        self.vcf.group_selected = (!self.group_rows().is_empty()).then_some(0);
+    }
+
+    /// Expands or collapses the group under the cursor, or the one holding
+    /// the selected record, leaving the cursor on its header.
+    pub fn toggle_group(&mut self) {
+        let rows = self.group_rows();
+        let header = self.vcf.group_selected.and_then(|i| {
+            rows.iter()
+                .take(i + 1)
+                .enumerate()
+                .rev()
+                .find_map(|(n, row)| match row {
+                    GroupRow::Group { value, .. } => Some((n, value.to_string())),
+                    GroupRow::Record(_) => None,
+                })
+        });
+        let Some((n, value)) = header else {
+            return;
+        };
+        if !self.vcf.expanded_groups.remove(&value) {
+            self.vcf.expanded_groups.insert(value);
+        }
+        self.vcf.group_selected = Some(n);
+    }
+
+    pub fn toggle_dimmed(&mut self) {
+        self.keep_selection(|app| app.vcf.show_dimmed = !app.vcf.show_dimmed);
+    }
+
+    /// Sorts the rows by `column` in the given direction, as from the
+    /// command palette.
+    pub fn set_sort(&mut self, column: Column, descending: bool) {
+        self.keep_selection(|app| {
+            app.vcf.sort_column = Some(column);
+            app.vcf.sort_descending = descending;
+        });
+        self.save_sort();
+    }
+
+    /// Sorts by `column`, flipping the direction if it is already the key.
+    pub fn sort_by_column(&mut self, column: Column) {
+        self.keep_selection(|app| {
+            if app.vcf.sort_column == Some(column) {
+                app.vcf.sort_descending = !app.vcf.sort_descending;
+            } else {
+                app.vcf.sort_column = Some(column);
+                app.vcf.sort_descending = false;
+            }
+        });
+        self.save_sort();
+    }
+
+    /// Keeps the sort in the config as e.g. `sort = QUAL desc` so it is
+    /// back on the next start; it already carries over between files.
+    fn save_sort(&mut self) {
+        let value = match self.vcf.sort_column {
+            Some(column) if self.vcf.sort_descending => format!("{} desc", column.label()),
+            Some(column) => format!("{} asc", column.label()),
+            None => "none".to_string(),
+        };
+        self.config.set("sort", &value);
+        let _ = self.config.save();
+    }
+
+    /// Jumps to the first displayed row of the next chromosome, or back to
+    /// the start of the current one (then the previous one).
+    pub fn jump_chrom(&mut self, forward: bool) {
+        let rows = self.display_rows();
+        let Some(current) = self.vcf.selected.filter(|&i| i < rows.len()) else {
+            self.vcf.selected = (!rows.is_empty()).then_some(0);
+            return;
+        };
+        let chrom_at = |i: usize| rows[i].0.chrom.as_str();
+        let target = if forward {
+            (current + 1..rows.len()).find(|&i| chrom_at(i) != chrom_at(current))
+        } else {
+            // Step back into the previous block when already at a block start.
+            let from = if current > 0 && chrom_at(current - 1) != chrom_at(current) {
+                current - 1
+            } else {
+                current
+            };
+            let start = (0..from)
+                .rev()
+                .find(|&i| chrom_at(i) != chrom_at(from))
+                .map_or(0, |i| i + 1);
+            (start != current).then_some(start)
+        };
+        if let Some(target) = target {
+            self.vcf.selected = Some(target);
+            self.vcf.detail_scroll = 0;
+        }
+    }
+
+    /// Moves the sort key to the next visible column, then back to file order.
+    pub fn cycle_sort_column(&mut self) {
+        self.keep_selection(|app| {
+            let columns = app.vcf.table_columns();
+            let next = match app.vcf.sort_column {
+                None => columns.first().copied(),
+                Some(c) => columns
+                    .iter()
+                    .position(|&v| v == c)
+                    .and_then(|i| columns.get(i + 1))
+                    .copied(),
+            };
+            app.vcf.sort_column = next;
+            app.vcf.sort_descending = false;
+        });
+        self.save_sort();
+    }
+
+    pub fn click_header(&mut self, x: u16, y: u16) -> bool {
+        let hit = self
+            .vcf
+            .header_cells
+            .iter()
+            .find(|(_, rect)| rect.contains(Position::new(x, y)))
+            .map(|&(column, _)| column);
+        if let Some(column) = hit {
+            self.sort_by_column(column);
+        }
+        hit.is_some()
+    }
+
+    /// Where the active text filter for `column` matched its displayed value.
+    pub fn filter_highlight(&self, column: Column, value: &str) -> Option<Range<usize>> {
+        let (filter, list) = match column {
+            Column::Chrom => (&self.vcf.filters.chrom, true),
+            Column::Ref => (&self.vcf.filters.ref_, false),
+            Column::Alt => (&self.vcf.filters.alt, false),
+            Column::Id => (&self.vcf.filters.id, false),
+            _ => return None,
+        };
+        filter_match(value, filter, self.vcf.filters.case_sensitive, list)
+    }
+
+    /// Up to `n` records either side of `record` on its chromosome, in POS
+    /// order, regardless of filters.
+    pub fn context_records<'a>(&'a self, record: &'a VcfRecord, n: usize) -> Vec<&'a VcfRecord> {
+        let records = &self.vcf.records;
+        let mut neighbours: Vec<&VcfRecord> = match &self.vcf.index {
+            // A sorted file is already in genomic order within each block.
+            Some(index) => index
+                .blocks
+                .iter()
+                .find(|(chrom, _)| *chrom == record.chrom)
+                .map(|(_, range)| records[range.clone()].iter().collect())
+                .unwrap_or_default(),
+            None => {
+                let mut same: Vec<&VcfRecord> =
+                    records.iter().filter(|r| r.chrom == record.chrom).collect();
+                same.sort_by_key(|r| r.pos);
+                same
+            }
+        };
+        let Some(at) = neighbours.iter().position(|r| std::ptr::eq(*r, record)) else {
+            return vec![record];
+        };
+        let end = (at + n + 1).min(neighbours.len());
+        neighbours.truncate(end);
+        neighbours.drain(..at.saturating_sub(n));
+        neighbours
+    }
+
+    /// Counts of filtered records on `chrom`, bucketed into `bins` equal
+    /// windows spanning that chromosome's lowest to highest POS.
+    pub fn density(&self, chrom: &str, bins: usize) -> Vec<u64> {
+        let positions: Vec<u64> = self
+            .filtered_records()
+            .into_iter()
+            .filter(|r| r.chrom == chrom)
+            .map(|r| r.pos)
+            .collect();
+        let mut counts = vec![0; bins];
+        let (Some(&min), Some(&max)) = (positions.iter().min(), positions.iter().max()) else {
+            return counts;
+        };
+        if bins == 0 {
+            return counts;
+        }
+        let span = max - min + 1;
+        for p in positions {
+            let bin = ((p - min) as u128 * bins as u128 / span as u128) as usize;
+            counts[bin] += 1;
+        }
+        counts
+    }
+
+    /// Chromosome shown on the Stats tab: the selected record's, else the
+    /// first one passing the filters.
+    pub fn stats_chrom(&self) -> Option<String> {
+        let rows = self.display_rows();
+        self.vcf
+            .selected
+            .and_then(|i| rows.get(i))
+            .or(rows.first())
+            .map(|(r, _)| r.chrom.clone())
+    }
+
+    /// Genotype tally of sample column `sample_idx` over the filtered
+    /// records.
+    pub fn genotype_summary(&self, sample_idx: usize) -> GtCounts {
+        GtCounts::from_records(self.filtered_records(), sample_idx)
+    }
+
+    /// `(biallelic, multi-allelic)` site counts over the filtered records.
+    /// Sites without an ALT allele count as neither.
+    pub fn allele_stats(&self) -> (usize, usize) {
+        self.filtered_records()
+            .iter()
+            .filter(|r| r.alt != ".")
+            .fold((0, 0), |(bi, multi), r| {
+                if r.is_multiallelic() {
+                    (bi, multi + 1)
+                } else {
+                    (bi + 1, multi)
+                }
+            })
+    }
+
+    /// Lowest and highest POS of the filtered records on each chromosome,
+    /// in the order the chromosomes first appear.
+    pub fn span(&self) -> Vec<(String, u64, u64)> {
+        let mut spans: Vec<(String, u64, u64)> = Vec::new();
+        for r in self.filtered_records() {
+            match spans.iter_mut().find(|(chrom, _, _)| *chrom == r.chrom) {
+                Some((_, min, max)) => {
+                    *min = (*min).min(r.pos);
+                    *max = (*max).max(r.pos);
+                }
+                None => spans.push((r.chrom.clone(), r.pos, r.pos)),
+            }
+        }
+        spans
+    }
+
+    /// Records per FILTER tag over every loaded record, ignoring the active
+    /// filters; `PASS;q10`-style values count towards each tag. Most common
+    /// first.
+    pub fn filter_counts(&self) -> Vec<(String, usize)> {
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for r in self.vcf.records.iter() {
+            for tag in r.filter.split(';').filter(|t| !t.is_empty()) {
+                *counts.entry(tag).or_default() += 1;
+            }
+        }
+        let mut counts: Vec<(String, usize)> = counts
+            .into_iter()
+            .map(|(tag, n)| (tag.to_string(), n))
+            .collect();
+        counts.sort_by(|(a, x), (b, y)| y.cmp(x).then_with(|| a.cmp(b)));
+        counts
+    }
+
+    /// Summary statistics over every loaded record, ignoring filters.
+    pub fn stats(&self) -> VariantStats {
+        VariantStats::from_records(self.vcf.records.iter())
+    }
+
+    /// Writes a markdown QC report: overall stats plus how many records pass
+    /// each active filter on its own and all of them together.
+    pub fn write_report(&self, out: &Path) -> io::Result<()> {
+        let stats = self.stats();
+        let mut report = String::new();
+        let source = match &self.vcf.path {
+            Some(path) => path.display().to_string(),
+            None => "-".to_string(),
+        };
+        report.push_str(&format!("# vcfscan report\n\nSource: `{}`\n\n", source));
+        if let Some((shown, matching)) = self.head_truncation() {
+            report.push_str(&format!(
+                "> The head cap lists only the first {} of {} matching records; \
+                 this report covers all {}.\n\n",
+                shown, matching, matching
+            ));
+        }
+
+        report.push_str("## Summary\n\n");
+        report.push_str(&format!("- Total variants: {}\n", stats.total));
+        report.push_str(&format!("- Parse warnings: {}\n", self.vcf.warnings.len()));
+        report.push_str(&format!("- SNPs: {}\n", stats.snps));
+        report.push_str(&format!("- Indels: {}\n", stats.indels));
+        report.push_str(&format!("- Other: {}\n", stats.other));
+        let ts_tv = match stats.ts_tv() {
+            Some(ratio) => format!("{:.3}", ratio),
+            None => "n/a".to_string(),
+        };
+        report.push_str(&format!(
+            "- Ts/Tv: {} ({} transitions, {} transversions)\n\n",
+            ts_tv, stats.transitions, stats.transversions
+        ));
+
+        report.push_str("## Variants per chromosome\n\n| CHROM | Count |\n|---|---|\n");
+        for (chrom, count) in &stats.per_chrom {
+            report.push_str(&format!("| {} | {} |\n", chrom, count));
+        }
+
+        if !self.vcf.samples.is_empty() {
+            report.push_str(
+                "\n## Genotypes per sample\n\n\
+                 | Sample | Hom-ref | Het | Hom-alt | Missing |\n|---|---|---|---|---|\n",
+            );
+            for (i, sample) in self.vcf.samples.iter().enumerate() {
+                let gt = self.genotype_summary(i);
+                report.push_str(&format!(
+                    "| {} | {} | {} | {} | {} |\n",
+                    sample, gt.hom_ref, gt.het, gt.hom_alt, gt.missing
+                ));
+            }
+        }
+
+        report.push_str("\n## Filters\n\n");
+        let active = self.active_filters();
+        if active.is_empty() {
+            report.push_str("No active filters.\n");
+        } else {
+            let pos_range = self.vcf.filters.pos_range();
+            report.push_str("| Filter | Value | Passing |\n|---|---|---|\n");
+            for &item in MENU_ITEMS {
+                let Some(value) = self.filter_value(item) else {
+                    continue;
+                };
+                let passing = self
+                    .vcf
+                    .records
+                    .iter()
+                    .filter(|r| self.vcf.filters.passes(item, r, &pos_range))
+                    .count();
+                report.push_str(&format!("| {} | {} | {} |\n", item.label(), value, passing));
+            }
+            report.push_str(&format!("| All | | {} |\n", self.filtered_records().len()));
+        }
+
+        fs::write(out, report)
+    }
+
+    /// The filtered records in display order as TSV, one line per record
+    /// under a line of column labels, using the table's columns. Stops
+    /// after `limit` records.
+    pub fn filtered_tsv(&self, limit: usize) -> String {
+        let columns = self.vcf.table_columns();
+        let mut lines = vec![
+            columns
+                .iter()
+                .map(|c| c.label())
+                .collect::<Vec<_>>()
+                .join("\t"),
+        ];
+        let mut rows: Vec<_> = self
+            .filtered_indexes()
+            .into_iter()
+            .map(|i| (i, true))
+            .collect();
+        self.sort_rows(&mut rows);
+        lines.extend(self.rows_at(rows).into_iter().take(limit).map(|(r, _)| {
+            columns
+                .iter()
+                .map(|c| c.value(r, &self.vcf))
+                .collect::<Vec<_>>()
+                .join("\t")
+        }));
+        lines.push(String::new());
+        lines.join("\n")
+    }
+
+    /// Puts the filtered records on the clipboard as TSV, capped at
+    /// `copy_limit` rows and at what fits in `COPY_MAX_ENCODED`. The
+    /// terminal does the copying (OSC 52), so this works over SSH but needs
+    /// a terminal that allows it.
+    pub fn copy_filtered(&mut self) {
+        if self.vcf.records.is_empty() {
+            self.notify("No VCF loaded".to_string());
+            return;
+        }
+        let total = self.filtered_records().len();
+        if total == 0 {
+            self.notify("Nothing to copy (no records match)".to_string());
+            return;
+        }
+        let mut tsv = self.filtered_tsv(self.copy_limit);
+        // Base64 turns every 3 bytes into 4; cut whole lines to fit.
+        let too_big = cut_to_lines(&mut tsv, COPY_MAX_ENCODED / 4 * 3);
+        // Less the line of column labels.
+        let copied = tsv.lines().count().saturating_sub(1);
+        if copied == 0 {
+            self.notify("Nothing copied (the first record alone is too long)".to_string());
+            return;
+        }
+        let written = write!(io::stdout(), "\x1b]52;c;{}\x07", STANDARD.encode(&tsv))
+            .and_then(|()| io::stdout().flush());
+        let text = match written {
+            Err(e) => format!("Copy failed: {}", e),
+            Ok(()) if too_big => format!(
+                "Copied the first {} of {} records as TSV (the terminal's clipboard size limit)",
+                copied, total
+            ),
+            Ok(()) if copied < total => format!(
+                "Copied the first {} of {} records as TSV (copy_limit = {})",
+                copied, total, self.copy_limit
+            ),
+            Ok(()) => format!("Copied {} records as TSV", copied),
+        };
+        self.notify(text);
+    }
+
+    /// Writes the report next to the loaded file as `<file>.report.md`.
+    pub fn export_report(&mut self) {
+        let Some(path) = &self.vcf.path else {
+            self.notify("No VCF loaded".to_string());
+            return;
+        };
+        let out = PathBuf::from(format!("{}.report.md", output_base(path).display()));
+        let text = match self.write_report(&out) {
+            Ok(()) => format!("Report written to {}", out.display()),
+            Err(e) => format!("Report failed: {}", e),
+        };
+        self.notify(text);
+    }
+
+    /// Shows `text` in the status bar until the next key or `TOAST_DURATION`.
+    pub fn notify(&mut self, text: String) {
+        self.message = Some((text, Instant::now()));
+    }
+
+    /// The filter string an input modal of `kind` edits, if it is one.
+    pub fn filter_field(&mut self, kind: ModalKind) -> Option<&mut String> {
+        Some(match kind {
+            ModalKind::Chrom => &mut self.vcf.filters.chrom,
+            ModalKind::Ref => &mut self.vcf.filters.ref_,
+            ModalKind::Alt => &mut self.vcf.filters.alt,
+            ModalKind::Pos => &mut self.vcf.filters.pos,
+            ModalKind::Id => &mut self.vcf.filters.id,
+            ModalKind::IndelLen => &mut self.vcf.filters.indel,
+            ModalKind::SvType => &mut self.vcf.filters.svtype,
+            ModalKind::Info => &mut self.vcf.filters.info,
+            ModalKind::Flag => &mut self.vcf.filters.flag,
+            _ => return None,
+        })
+    }
+
+    /// Recounts the records that would match if the open input modal's text
+    /// replaced its filter, leaving the other filters as they are.
+    pub fn refresh_match_count(&mut self) {
+        let Some(modal) = &self.modal else {
+            return;
+        };
+        let (kind, mut candidate) = (modal.kind, modal.input.trim().to_string());
+        let count = match self.filter_field(kind) {
+            Some(field) => {
+                std::mem::swap(field, &mut candidate);
+                let count = self.filtered_records().len();
+                if let Some(field) = self.filter_field(kind) {
+                    std::mem::swap(field, &mut candidate);
+                }
+                Some(count)
+            }
+            None => None,
+        };
+        if let Some(modal) = &mut self.modal {
+            modal.match_count = count;
+            modal.count_due = None;
+        }
+    }
+
+    /// Reads the open file again; rows it adds or changes are highlighted
+    /// for a few seconds.
+    pub fn reload(&mut self) {
+        if self.files.from_stdin || self.vcf.combined {
+            self.notify("Only a single file can be reloaded".to_string());
+            return;
+        }
+        let Some(path) = self.files.loaded.clone() else {
+            self.notify("No VCF loaded".to_string());
+            return;
+        };
+        self.select_path(path);
+        self.pending_load = true;
+    }
+
+    /// How `r` differs from before the last reload, while that is still
+    /// highlighted.
+    pub fn record_change(&self, r: &VcfRecord) -> Option<RecordChange> {
+        let changes = self.vcf.changes.as_ref()?;
+        if changes.at.elapsed() >= CHANGE_HIGHLIGHT {
+            return None;
+        }
+        changes.records.get(&BookmarkKey::of(r)).copied()
+    }
+
+    /// Time left before the reload highlight expires.
+    pub fn changes_remaining(&self) -> Option<Duration> {
+        let changes = self.vcf.changes.as_ref()?;
+        Some(CHANGE_HIGHLIGHT.saturating_sub(changes.at.elapsed()))
+    }
+
+    /// Time left before the current toast expires.
+    pub fn toast_remaining(&self) -> Option<Duration> {
+        let (_, raised) = self.message.as_ref()?;
+        Some(TOAST_DURATION.saturating_sub(raised.elapsed()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vcf::parse_vcf_reader;
+    use std::io::Cursor;
+
+    #[test]
+    fn filtered_records_apply_every_filter() {
+        let data = "1\t150\t.\tA\tG\t.\t.\t.\n\
+                    20\t150\t.\tA\tG\t.\t.\t.\n\
+                    20\t250\t.\tA\tG\t.\t.\t.\n";
+        let mut app = App::default();
+        app.set_records(parse_vcf_reader(Cursor::new(data)).unwrap());
+        assert_eq!(app.filtered_records().len(), 3);
+
+        app.vcf.filters.chrom = "20".into();
+        app.vcf.filters.pos = "100-200".into();
+        let hits: Vec<(&str, u64)> = app
+            .filtered_records()
+            .iter()
+            .map(|r| (r.chrom.as_str(), r.pos))
+            .collect();
+        assert_eq!(hits, vec![("20", 150)]);
+    }
+
+    #[test]
+    fn editor_opens_at_the_selected_line() {
+        let mut app = App::default();
+        app.open_in_editor();
+        assert!(app.pending_editor.is_none());
+        let data = "##fileformat=VCFv4.2\n1\t10\t.\tA\tG\t.\t.\t.\n";
+        app.set_records(parse_vcf_reader(Cursor::new(data)).unwrap());
+        app.vcf.path = Some(PathBuf::from("in.vcf"));
+        app.vcf.selected = Some(0);
+        app.open_in_editor();
+        assert_eq!(app.pending_editor, Some((PathBuf::from("in.vcf"), Some(2))));
+    }
+
+    #[test]
+    fn sort_outlives_a_new_file() {
+        let mut app = App::default();
+        app.set_sort(Column::Qual, true);
+        assert_eq!(app.config.get("sort"), Some("QUAL desc"));
+        app.set_records(parse_vcf_reader(Cursor::new("1\t10\t.\tA\tG\t5\t.\t.\n")).unwrap());
+        assert_eq!(app.vcf.sort_column, Some(Column::Qual));
+        assert_eq!(parse_sort("qual desc"), Some((Column::Qual, true)));
+        assert_eq!(parse_sort("none"), None);
+    }
+
+    #[test]
+    fn head_caps_the_listed_records() {
+        let data = "1\t10\t.\tA\tG\t.\t.\t.\n\
+                    1\t20\t.\tA\tG\t.\t.\t.\n\
+                    1\t30\t.\tA\tG\t.\t.\t.\n";
+        let mut app = App::default();
+        app.set_records(parse_vcf_reader(Cursor::new(data)).unwrap());
+        app.set_head(Some(2));
+        let pos: Vec<u64> = app
+            .records_at(app.listed_indexes())
+            .iter()
+            .map(|r| r.pos)
+            .collect();
+        assert_eq!(pos, vec![10, 20]);
+        assert_eq!(app.filtered_records().len(), 3);
+        assert_eq!(app.filtered_tsv(10).lines().count(), 4);
+        assert_eq!(app.head_truncation(), Some((2, 3)));
+        let out = std::env::temp_dir().join(format!("vcfscan-head-{}.md", std::process::id()));
+        app.write_report(&out).unwrap();
+        let report = fs::read_to_string(&out).unwrap();
+        let _ = fs::remove_file(&out);
+        assert!(report.contains("first 2 of 3 matching records"));
+        app.vcf.show_dimmed = true;
+        assert_eq!(app.classify_records().iter().filter(|(_, m)| *m).count(), 2);
+        app.set_head(None);
+        assert_eq!(app.records_at(app.listed_indexes()).len(), 3);
+        assert_eq!(app.head_truncation(), None);
+    }
+
+    #[test]
+    fn allele_stats_split_biallelic_and_multiallelic() {
+        let data = "1\t10\t.\tA\tG\t.\t.\t.\n\
+                    1\t20\t.\tA\tG,T\t.\t.\t.\n\
+                    1\t30\t.\tA\t.\t.\t.\t.\n";
+        let mut app = App::default();
+        app.set_records(parse_vcf_reader(Cursor::new(data)).unwrap());
+        assert_eq!(app.allele_stats(), (1, 1));
+
+        app.vcf.filters.multiallelic_only = true;
+        assert_eq!(app.allele_stats(), (0, 1));
+    }
+
+    #[test]
+    fn filter_counts_split_combined_tags() {
+        let data = "1\t10\t.\tA\tG\t.\tPASS\t.\n\
+                    1\t20\t.\tA\tG\t.\tq10;LowQual\t.\n\
+                    1\t30\t.\tA\tG\t.\tq10\t.\n\
+                    1\t40\t.\tA\tG\t.\t.\t.\n";
+        let mut app = App::default();
+        app.set_records(parse_vcf_reader(Cursor::new(data)).unwrap());
+        let counts = app.filter_counts();
+        let counts: Vec<(&str, usize)> = counts.iter().map(|(t, n)| (t.as_str(), *n)).collect();
+        assert_eq!(counts, [("q10", 2), (".", 1), ("LowQual", 1), ("PASS", 1)]);
+    }
+
+    #[test]
+    fn recent_files_are_capped_and_deduplicated() {
+        let mut app = App::default();
+        for i in 0..12 {
+            app.remember_recent(Path::new(&format!("/data/{}.vcf", i)));
+        }
+        app.remember_recent(Path::new("/data/5.vcf"));
+        assert_eq!(app.recent.len(), RECENT_LIMIT);
+        assert_eq!(app.recent[0], Path::new("/data/5.vcf"));
+        assert_eq!(app.recent[1], Path::new("/data/11.vcf"));
+        assert_eq!(
+            app.recent.iter().filter(|p| p.ends_with("5.vcf")).count(),
+            1
+        );
+    }
+
+    #[test]
+    fn export_runs_in_the_background() {
+        let data = "1\t10\t.\tA\tG\t.\t.\t.\n1\t20\t.\tC\tT\t.\t.\t.\n";
+        let mut app = App::default();
+        app.set_records(parse_vcf_reader(Cursor::new(data)).unwrap());
+        app.vcf.path = Some(PathBuf::from("in.vcf"));
+        let out = env::temp_dir().join(format!("vcfscan-export-{}.bed", std::process::id()));
+        app.vcf.filters.ref_ = "C".to_string();
+        app.export_bed(&out);
+        let started = Instant::now();
+        while !app.poll_export() {
+            assert!(started.elapsed() < Duration::from_secs(5));
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert!(app.export.is_none());
+        assert_eq!(fs::read_to_string(&out).unwrap(), "1\t19\t20\n");
+        assert!(!Path::new(&format!("{}.part", out.display())).exists());
+        fs::remove_file(&out).unwrap();
+    }
+
+    #[test]
+    fn reload_marks_added_and_changed_records() {
+        let path = env::temp_dir().join(format!("vcfscan-reload-{}.vcf", std::process::id()));
+        let header = "##fileformat=VCFv4.2\n";
+        fs::write(
+            &path,
+            format!(
+                "{}1\t10\t.\tA\tG\t5\tPASS\t.\n1\t20\t.\tC\tT\t5\tPASS\t.\n",
+                header
+            ),
+        )
+        .unwrap();
+        let mut app = App::default();
+        app.select_path(path.clone());
+        app.load_selected_vcf(None);
+        assert!(app.vcf.changes.is_none());
+
+        fs::write(
+            &path,
+            format!(
+                "{}1\t10\t.\tA\tG\t5\tq10\t.\n1\t30\t.\tG\tA\t5\tPASS\t.\n",
+                header
+            ),
+        )
+        .unwrap();
+        app.reload();
+        assert!(app.pending_load);
+        app.load_selected_vcf(None);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(app.vcf.changes.as_ref().map(|c| c.removed), Some(1));
+        let changes: Vec<_> = app
+            .vcf
+            .records
+            .iter()
+            .map(|r| app.record_change(r))
+            .collect();
+        assert_eq!(
+            changes,
+            [Some(RecordChange::Changed), Some(RecordChange::Added)]
+        );
+    }
+
+    #[test]
+    fn span_covers_filtered_records_per_chrom() {
+        let data = "2\t500\t.\tA\tG\t.\t.\t.\n\
+                    1\t300\t.\tA\tG\t.\t.\t.\n\
+                    2\t50\t.\tA\tG\t.\t.\t.\n\
+                    1\t900\t.\tC\tT\t.\t.\t.\n";
+        let mut app = App::default();
+        app.set_records(parse_vcf_reader(Cursor::new(data)).unwrap());
+        assert_eq!(
+            app.span(),
+            [("2".to_string(), 50, 500), ("1".to_string(), 300, 900)]
+        );
+        app.vcf.filters.ref_ = "A".to_string();
+        assert_eq!(
+            app.span(),
+            [("2".to_string(), 50, 500), ("1".to_string(), 300, 300)]
+        );
+    }
+
+    #[test]
+    fn info_groups_bucket_records_and_expand() {
+        let data = "1\t100\t.\tA\t<DEL>\t.\t.\tSVTYPE=DEL\n\
+                    1\t200\t.\tA\tG\t.\t.\tDP=3\n\
+                    1\t300\t.\tA\t<DUP>\t.\t.\tSVTYPE=DUP\n\
+                    1\t400\t.\tA\t<DEL>\t.\t.\tSVTYPE=DEL\n";
+        let mut app = App::default();
+        app.set_records(parse_vcf_reader(Cursor::new(data)).unwrap());
+        app.set_group_by(Some("SVTYPE".to_string()));
+        let groups: Vec<(&str, usize)> = app
+            .info_groups()
+            .iter()
+            .map(|(value, records)| (*value, records.len()))
+            .collect();
+        assert_eq!(groups, [("DEL", 2), ("DUP", 1), (ABSENT_GROUP, 1)]);
+        assert_eq!(app.group_rows().len(), 3);
+        assert!(app.selected_record().is_none());
+
+        app.toggle_group();
+        assert_eq!(app.group_rows().len(), 5);
+        app.vcf.group_selected = Some(2);
+        assert_eq!(app.selected_record().map(|r| r.pos), Some(400));
+        // Collapsing from a record puts the cursor back on its header.
+        app.toggle_group();
+        assert_eq!(app.group_rows().len(), 3);
+        assert_eq!(app.vcf.group_selected, Some(0));
+
+        // The cached groups follow the filters.
+        app.vcf.filters.pos = "300-400".to_string();
+        let groups: Vec<(&str, usize)> = app
+            .info_groups()
+            .iter()
+            .map(|(value, records)| (*value, records.len()))
+            .collect();
+        assert_eq!(groups, [("DUP", 1), ("DEL", 1)]);
+    }
+
+    #[test]
+    fn cut_to_lines_keeps_whole_lines() {
+        let mut text = "POS\tID\n100\trs1\n200\tcafé\n".to_string();
+        // The cap lands between the two bytes of `é`.
+        let cap = text.find('é').unwrap() + 1;
+        assert!(cut_to_lines(&mut text, cap));
+        assert_eq!(text, "POS\tID\n100\trs1\n");
+        assert!(!cut_to_lines(&mut text, 100));
+    }
+
+    #[test]
+    fn filtered_tsv_uses_table_columns_and_limit() {
+        let data = "1\t100\t.\tA\tG\t.\t.\t.\n\
+                    1\t200\t.\tC\tT\t.\t.\t.\n\
+                    1\t300\t.\tA\tT\t.\t.\t.\n";
+        let mut app = App::default();
+        app.set_records(parse_vcf_reader(Cursor::new(data)).unwrap());
+        app.vcf.visible_columns = vec![Column::Pos, Column::Ref, Column::Alt];
+        app.vcf.filters.ref_ = "A".to_string();
+        assert_eq!(
+            app.filtered_tsv(10),
+            "POS\tREF\tALT\n100\tA\tG\n300\tA\tT\n"
+        );
+        assert_eq!(app.filtered_tsv(1), "POS\tREF\tALT\n100\tA\tG\n");
+    }
+
+    #[test]
+    fn modal_input_edits_at_the_cursor() {
+        let mut modal = ModalState::new_input(ModalKind::Pos);
+        modal.set_input("1000-500".to_string());
+        modal.move_cursor(5);
+        modal.insert('5');
+        assert_eq!(modal.input, "1000-5500");
+        modal.move_cursor(0);
+        modal.delete(false);
+        modal.delete(true);
+        assert_eq!(modal.input, "000-5500");
+        modal.move_cursor(usize::MAX);
+        modal.delete(true);
+        modal.delete(false);
+        assert_eq!((modal.input.as_str(), modal.cursor), ("000-550", 7));
+
+        modal.set_input("sort qual  desc".to_string());
+        modal.move_cursor(11);
+        modal.delete_word();
+        assert_eq!((modal.input.as_str(), modal.cursor), ("sort desc", 5));
+
+        // Multibyte whitespace (U+3000, U+00A0) separates words too.
+        modal.set_input("AF\u{3000}DP\u{a0}GQ".to_string());
+        modal.delete_word();
+        assert_eq!(
+            (modal.input.as_str(), modal.cursor),
+            ("AF\u{3000}DP\u{a0}", 6)
+        );
+        modal.move_cursor(3);
+        modal.delete_word();
+        assert_eq!((modal.input.as_str(), modal.cursor), ("DP\u{a0}", 0));
+    }
+
+    #[test]
+    fn sort_records_restores_coordinate_order() {
+        let data = "1\t300\t.\tA\tG\t.\t.\t.\n\
+                    2\t5\t.\tA\tG\t.\t.\t.\n\
+                    1\t100\t.\tA\tG\t.\t.\t.\n";
+        let mut app = App::default();
+        app.set_records(parse_vcf_reader(Cursor::new(data)).unwrap());
+        assert!(!app.vcf.is_sorted());
+
+        app.sort_records();
+        assert!(app.vcf.is_sorted());
+        let order: Vec<(&str, u64)> = app
+            .vcf
+            .records
+            .iter()
+            .map(|r| (r.chrom.as_str(), r.pos))
+            .collect();
+        assert_eq!(order, [("1", 100), ("1", 300), ("2", 5)]);
+    }
+
+    #[test]
+    fn visible_files_rank_fuzzy_matches() {
+        let mut app = App::default();
+        app.files.items = ["sample1.exome.vcf", "other.vcf", "s1_ex_m.vcf"]
+            .iter()
+            .map(PathBuf::from)
+            .collect();
+        app.set_file_filter("s1exm".into());
+        let names: Vec<&str> = app
+            .visible_files()
+            .iter()
+            .map(|p| p.to_str().unwrap())
+            .collect();
+        assert_eq!(names, ["s1_ex_m.vcf", "sample1.exome.vcf"]);
+    }
+
+    #[test]
+    fn scrolled_columns_keep_key_columns_pinned() {
+        let mut vcf = VcfState {
+            visible_columns: ALL_COLUMNS[1..].to_vec(),
+            ..Default::default()
+        };
+        vcf.scroll_columns(true);
+        vcf.scroll_columns(true);
+        assert_eq!(
+            vcf.scrolled_columns(),
+            [
+                Column::Chrom,
+                Column::Pos,
+                Column::Alt,
+                Column::Qual,
+                Column::Filter,
+                Column::Info
+            ]
+        );
+        for _ in 0..10 {
+            vcf.scroll_columns(true);
+        }
+        assert_eq!(
+            vcf.scrolled_columns(),
+            [Column::Chrom, Column::Pos, Column::Info]
+        );
+    }
+
+    #[test]
+    fn poll_scan_keeps_the_selected_file() {
+        let (tx, rx) = mpsc::channel();
+        let mut app = App::default();
+        app.files.scan = Some(rx);
+        tx.send(PathBuf::from("b.vcf")).unwrap();
+        assert!(app.poll_scan());
+        assert_eq!(app.selected_file(), Some(&PathBuf::from("b.vcf")));
+
+        app.set_file_filter("vcf".into());
+        tx.send(PathBuf::from("a.vcf")).unwrap();
+        drop(tx);
+        assert!(app.poll_scan());
+        assert!(app.files.scan.is_none());
+        assert_eq!(app.files.items.len(), 2);
+        assert_eq!(app.selected_file(), Some(&PathBuf::from("b.vcf")));
+    }
+
+    #[test]
+    fn clamp_selection_follows_shrinking_filters() {
+        let data = "1\t10\t.\tA\tG\t.\t.\t.\n\
+                    1\t20\t.\tA\tG\t.\t.\t.\n\
+                    2\t30\t.\tA\tG\t.\t.\t.\n";
+        let mut app = App::default();
+        app.set_records(parse_vcf_reader(Cursor::new(data)).unwrap());
+        app.vcf.selected = Some(2);
+
+        app.vcf.filters.chrom = "1".into();
+        app.clamp_selection();
+        assert_eq!(app.vcf.selected, Some(1));
+
+        app.vcf.filters.chrom = "X".into();
+        app.clamp_selection();
+        assert_eq!(app.vcf.selected, None);
+    }
+}
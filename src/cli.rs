@@ -0,0 +1,366 @@
+//! Command-line arguments and the headless `--print` mode.
+
+use std::{
+    error::Error,
+    io::{self, BufRead, BufReader, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    app::{Column, Config},
+    filter::{BedMode, FilterSet, apply_filters},
+    vcf::{open_input, parse_bed, parse_vcf_bytes, vcf_text, write_vcf_lines},
+};
+
+/// `vcfscan [FILE|URL|-] [--chrom X] [--pos A-B] ... [--print]`; see `USAGE`.
+#[derive(Debug, Default)]
+pub struct Cli {
+    pub path: Option<PathBuf>, // `-` reads stdin
+    pub filters: FilterSet,
+    pub print: bool, // write matching records to stdout instead of starting the TUI
+    pub delimiter: Option<char>,
+    pub help: bool,
+    pub version: bool,
+    pub percent_decode: bool,
+    pub head: Option<usize>, // only the first N matching records
+    pub summary_json: bool,  // with `--print`, a JSON summary line on stderr
+}
+
+/// Printed by `--help`.
+pub const USAGE: &str = "\
+vcfscan - a terminal viewer for VCF and BCF files
+
+Usage: vcfscan [FILE|URL|-] [OPTIONS]
+
+With no FILE the current directory is scanned for VCF/BCF files.
+An http:// or https:// URL is downloaded; `-` reads a VCF or BCF stream
+from stdin. Either may be gzipped.
+
+Filters (also used by --print):
+  --chrom LIST        CHROM values, a,b matches either, !x excludes
+  --pos RANGE         12345, 1000-5000, 1000- or -5000
+  --region REGION     CHROM and POS at once, e.g. chr1:1,000-5,000
+  --id TEXT           ID filter
+  --ref TEXT          REF filter
+  --alt TEXT          ALT filter
+  --indel EXPR        indel size, e.g. >=5
+  --svtype LIST       SVTYPE values, e.g. DEL,DUP
+  --info EXPR         INFO test, e.g. DP>=10, DB or GENE=x
+  --flag KEYS         INFO flags that must be set (no value), e.g. SOMATIC,DB
+  --bed FILE          keep records inside the BED regions
+  --non-missing COLS  columns that may not be `.`, e.g. ID,QUAL
+  --multiallelic      only multi-allelic sites
+  --case-sensitive    match text filters case-sensitively
+  --head N            only the first N matching records (N toggles it in the TUI)
+
+Other options:
+  --delimiter CHAR    column separator (tab, space, comma, ... or a character)
+  --percent-decode    show %3A-style escapes in ID and INFO decoded in the detail panel
+  --print             write the matching records to stdout instead of starting the TUI
+  --summary-json      with --print, write a one-line JSON summary (records loaded and
+                      matched, filters, parse warnings) to stderr
+  -h, --help          show this help
+  -V, --version       show the version
+";
+
+/// Reads a `--delimiter` value: a single character or one of the names
+/// `tab`, `space`, `comma`, `semicolon`, `pipe` (also `\t`).
+fn parse_delimiter(value: &str) -> Option<char> {
+    match value {
+        "tab" | "\\t" => Some('\t'),
+        "space" => Some(' '),
+        "comma" => Some(','),
+        "semicolon" => Some(';'),
+        "pipe" => Some('|'),
+        _ => {
+            let mut chars = value.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Some(c),
+                _ => None,
+            }
+        }
+    }
+}
+
+impl Cli {
+    /// Parses the arguments after the program name. Filter flags take their
+    /// value as the next argument or after `=`, e.g. `--pos=100-200`.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut cli = Cli::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            if arg == "--print" {
+                cli.print = true;
+                continue;
+            }
+            if arg == "-h" || arg == "--help" {
+                cli.help = true;
+                continue;
+            }
+            if arg == "-V" || arg == "--version" {
+                cli.version = true;
+                continue;
+            }
+            if arg == "--summary-json" {
+                cli.summary_json = true;
+                continue;
+            }
+            if arg == "--percent-decode" {
+                cli.percent_decode = true;
+                continue;
+            }
+            if arg == "--case-sensitive" {
+                cli.filters.case_sensitive = true;
+                continue;
+            }
+            if arg == "--multiallelic" {
+                cli.filters.multiallelic_only = true;
+                continue;
+            }
+            if arg == "-" || !arg.starts_with('-') {
+                if cli.path.replace(PathBuf::from(&arg)).is_some() {
+                    return Err(format!("unexpected argument '{}'", arg));
+                }
+                continue;
+            }
+            let (flag, inline) = match arg.split_once('=') {
+                Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+                None => (arg.clone(), None),
+            };
+            let field = match flag.as_str() {
+                "--chrom" => &mut cli.filters.chrom,
+                "--ref" => &mut cli.filters.ref_,
+                "--alt" => &mut cli.filters.alt,
+                "--pos" => &mut cli.filters.pos,
+                "--id" => &mut cli.filters.id,
+                "--indel" => &mut cli.filters.indel,
+                "--svtype" => &mut cli.filters.svtype,
+                "--info" => &mut cli.filters.info,
+                "--flag" => &mut cli.filters.flag,
+                "--bed" => {
+                    let value = inline
+                        .or_else(|| args.next())
+                        .ok_or_else(|| format!("{} needs a value", flag))?;
+                    let bed =
+                        parse_bed(Path::new(&value)).map_err(|e| format!("{}: {}", value, e))?;
+                    cli.filters.bed = Some(bed);
+                    cli.filters.bed_mode = BedMode::Inside;
+                    continue;
+                }
+                "--non-missing" => {
+                    let value = inline
+                        .or_else(|| args.next())
+                        .ok_or_else(|| format!("{} needs a value", flag))?;
+                    for label in value.split(',') {
+                        let column = Column::from_label(label)
+                            .filter(|c| c.may_be_missing())
+                            .ok_or_else(|| format!("--non-missing: no column '{}'", label))?;
+                        cli.filters.non_missing.push(column);
+                    }
+                    continue;
+                }
+                "--region" => {
+                    let value = inline
+                        .or_else(|| args.next())
+                        .ok_or_else(|| format!("{} needs a value", flag))?;
+                    if !cli.filters.set_region(&value) {
+                        return Err(format!("invalid region '{}'", value));
+                    }
+                    continue;
+                }
+                "--head" => {
+                    let value = inline
+                        .or_else(|| args.next())
+                        .ok_or_else(|| format!("{} needs a value", flag))?;
+                    let n = value
+                        .parse()
+                        .ok()
+                        .filter(|&n| n > 0)
+                        .ok_or_else(|| format!("invalid --head count '{}'", value))?;
+                    cli.head = Some(n);
+                    continue;
+                }
+                "--delimiter" => {
+                    let value = inline
+                        .or_else(|| args.next())
+                        .ok_or_else(|| format!("{} needs a value", flag))?;
+                    let delimiter = parse_delimiter(&value)
+                        .ok_or_else(|| format!("invalid delimiter '{}'", value))?;
+                    cli.delimiter = Some(delimiter);
+                    continue;
+                }
+                _ => return Err(format!("unknown option '{}'", flag)),
+            };
+            *field = inline
+                .or_else(|| args.next())
+                .ok_or_else(|| format!("{} needs a value", flag))?;
+        }
+        if cli.filters.pos_invalid() {
+            return Err(format!("invalid POS filter '{}'", cli.filters.pos));
+        }
+        if cli.summary_json && !cli.print {
+            return Err("--summary-json needs --print".to_string());
+        }
+        Ok(cli)
+    }
+}
+
+/// Loads the input, applies the filters and writes the matching records to
+/// stdout as VCF.
+pub fn run_print(cli: &Cli) -> Result<(), Box<dyn Error>> {
+    let comments = Config::load().comment_prefixes();
+    let input: Box<dyn Read + Send> = match cli.path.as_deref() {
+        Some(path) if path == Path::new("-") => Box::new(io::stdin()),
+        Some(path) => open_input(path)?,
+        None => return Err("--print needs a FILE or URL (or - for stdin)".into()),
+    };
+    print_vcf(cli, BufReader::new(input), &comments, io::stdout().lock())
+}
+
+/// The `--print` output for `input`: its header and matching record lines,
+/// unchanged.
+fn print_vcf(
+    cli: &Cli,
+    input: impl BufRead,
+    comments: &[String],
+    out: impl Write,
+) -> Result<(), Box<dyn Error>> {
+    let text = vcf_text(input)?;
+    let parsed = parse_vcf_bytes(&text, cli.delimiter, comments)?;
+    for warning in &parsed.warnings {
+        eprintln!("vcfscan: {}", warning);
+    }
+    let mut records = apply_filters(&parsed.records, &cli.filters);
+    let matched = records.len();
+    if let Some(n) = cli.head {
+        records.truncate(n);
+    }
+    let written = write_vcf_lines(out, &text, &records);
+    if cli.summary_json {
+        eprintln!(
+            "{}",
+            summary_json(
+                parsed.records.len(),
+                matched,
+                &cli.filters,
+                parsed.warnings.len()
+            )
+        );
+    }
+    match written {
+        // Stopped early by the reader, e.g. `| head`.
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        result => Ok(result?),
+    }
+}
+
+/// The `--summary-json` line, e.g.
+/// `{"loaded":9,"matched":2,"filters":{"CHROM":"20"},"warnings":0}`.
+fn summary_json(loaded: usize, matched: usize, filters: &FilterSet, warnings: usize) -> String {
+    let filters: Vec<String> = filters
+        .active()
+        .iter()
+        .map(|(name, value)| format!("{}:{}", json_string(name), json_string(value)))
+        .collect();
+    format!(
+        "{{\"loaded\":{},\"matched\":{},\"filters\":{{{}}},\"warnings\":{}}}",
+        loaded,
+        matched,
+        filters.join(","),
+        warnings
+    )
+}
+
+/// `value` as a quoted JSON string.
+fn json_string(value: &str) -> String {
+    let mut out = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Cli, String> {
+        Cli::parse(args.iter().map(|a| a.to_string()))
+    }
+
+    #[test]
+    fn parses_path_filters_and_print() {
+        let cli = parse(&["in.vcf", "--chrom", "chr1", "--pos=1000-5000", "--print"]).unwrap();
+        assert_eq!(cli.path.as_deref(), Some(Path::new("in.vcf")));
+        assert_eq!(cli.filters.chrom, "chr1");
+        assert_eq!(cli.filters.pos, "1000-5000");
+        assert!(cli.print);
+    }
+
+    #[test]
+    fn print_keeps_sample_columns() {
+        let data = include_bytes!("../sample/sample.vcf");
+        let cli = parse(&["-", "--chrom", "20", "--print"]).unwrap();
+        let mut out = Vec::new();
+        print_vcf(&cli, &data[..], &[], &mut out).unwrap();
+        let expected: String = std::str::from_utf8(data)
+            .unwrap()
+            .lines()
+            .filter(|line| line.starts_with('#') || line.starts_with("20\t"))
+            .map(|line| format!("{}\n", line))
+            .collect();
+        assert_eq!(String::from_utf8(out).unwrap(), expected);
+        assert!(expected.contains("\tNA00001"));
+        assert!(expected.contains("\t0|0:48:1:51,51"));
+    }
+
+    #[test]
+    fn rejects_bad_arguments() {
+        assert!(parse(&["--chrom"]).is_err());
+        assert!(parse(&["--frobnicate"]).is_err());
+        assert!(parse(&["a.vcf", "b.vcf"]).is_err());
+        assert!(parse(&["--pos", "5000-1000"]).is_err());
+        assert!(parse(&["--delimiter", "ab"]).is_err());
+        assert!(parse(&["--head", "0"]).is_err());
+    }
+
+    #[test]
+    fn parses_help_and_version() {
+        assert!(parse(&["--help"]).unwrap().help);
+        assert!(parse(&["in.vcf", "-h"]).unwrap().help);
+        assert!(parse(&["-V"]).unwrap().version);
+        assert!(parse(&["-x"]).is_err());
+    }
+
+    #[test]
+    fn summary_json_lists_counts_and_filters() {
+        let cli = parse(&[
+            "--chrom",
+            "20",
+            "--info",
+            "GENE=\"a\"",
+            "--print",
+            "--summary-json",
+        ])
+        .unwrap();
+        assert_eq!(
+            summary_json(9, 2, &cli.filters, 1),
+            r#"{"loaded":9,"matched":2,"filters":{"CHROM":"20","INFO":"GENE=\"a\""},"warnings":1}"#
+        );
+        assert!(parse(&["--summary-json"]).is_err());
+    }
+
+    #[test]
+    fn parses_delimiter_names() {
+        assert_eq!(parse(&["--delimiter=comma"]).unwrap().delimiter, Some(','));
+        assert_eq!(parse(&["--delimiter", " "]).unwrap().delimiter, Some(' '));
+    }
+}
@@ -0,0 +1,174 @@
+//! The `:` command palette: parsing typed commands such as
+//! `sort qual desc` and completing their names.
+
+use std::path::PathBuf;
+
+use crate::{
+    app::{Column, ModalKind},
+    filter::common_prefix_len,
+    input::{ALL_ACTIONS, Action},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Sort(Column, bool),        // column, descending
+    Filter(ModalKind, String), // an empty value clears the filter
+    Export(Option<PathBuf>),   // filtered records, to the default path if none
+    Bed(PathBuf),
+    Tab(usize),
+    Group(Option<String>), // INFO key to group the list by; none ungroups
+    Head(Option<usize>),   // cap the list at the first N matches; none toggles
+    Action(Action),        // any key map action by its config name
+}
+
+/// Command names besides the action names, which are accepted too.
+const COMMANDS: &[&str] = &[
+    "sort", "filter", "clear", "export", "bed", "group", "head", "files", "variants", "stats",
+    "report", "quit",
+];
+
+const FILTER_FIELDS: &[(&str, ModalKind)] = &[
+    ("chrom", ModalKind::Chrom),
+    ("pos", ModalKind::Pos),
+    ("region", ModalKind::Region),
+    ("id", ModalKind::Id),
+    ("ref", ModalKind::Ref),
+    ("alt", ModalKind::Alt),
+    ("indel", ModalKind::IndelLen),
+    ("svtype", ModalKind::SvType),
+    ("info", ModalKind::Info),
+    ("flag", ModalKind::Flag),
+];
+
+/// Parses one palette line, e.g. `sort qual desc`, `filter chrom chr1`,
+/// `export out.vcf` or `toggle_legend`.
+pub fn parse_command(line: &str) -> Result<Command, String> {
+    let line = line.trim();
+    let (name, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    let rest = rest.trim();
+    match name.to_ascii_lowercase().as_str() {
+        "" => Err("No command given".to_string()),
+        "sort" if rest.is_empty() => Ok(Command::Action(Action::SortRecords)),
+        "sort" => {
+            let mut args = rest.split_whitespace();
+            let label = args.next().unwrap_or_default();
+            let column =
+                Column::from_label(label).ok_or_else(|| format!("Unknown column '{}'", label))?;
+            let descending = match args.next().map(str::to_ascii_lowercase).as_deref() {
+                None | Some("asc") => false,
+                Some("desc") => true,
+                Some(other) => return Err(format!("Expected asc or desc, got '{}'", other)),
+            };
+            Ok(Command::Sort(column, descending))
+        }
+        "filter" => {
+            let (field, value) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+            let kind = FILTER_FIELDS
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(field))
+                .map(|&(_, kind)| kind)
+                .ok_or_else(|| {
+                    let names: Vec<&str> = FILTER_FIELDS.iter().map(|(name, _)| *name).collect();
+                    format!("Unknown filter '{}' (one of {})", field, names.join(", "))
+                })?;
+            Ok(Command::Filter(kind, value.trim().to_string()))
+        }
+        "clear" => Ok(Command::Action(Action::ResetFilters)),
+        "export" => Ok(Command::Export((!rest.is_empty()).then(|| rest.into()))),
+        "bed" if rest.is_empty() => Err("Usage: bed PATH".to_string()),
+        "bed" => Ok(Command::Bed(rest.into())),
+        "group" => Ok(Command::Group((!rest.is_empty()).then(|| rest.to_string()))),
+        "head" if rest.is_empty() => Ok(Command::Head(None)),
+        "head" => match rest.parse() {
+            Ok(n) if n > 0 => Ok(Command::Head(Some(n))),
+            _ => Err(format!("Expected a record count, got '{}'", rest)),
+        },
+        "files" => Ok(Command::Tab(0)),
+        "variants" => Ok(Command::Tab(1)),
+        "stats" => Ok(Command::Tab(2)),
+        "report" => Ok(Command::Action(Action::WriteReport)),
+        other => ALL_ACTIONS
+            .iter()
+            .find(|action| action.name() == other)
+            .map(|&action| Command::Action(action))
+            .ok_or_else(|| format!("Unknown command '{}'", name)),
+    }
+}
+
+/// Command names that complete the name typed in `input`; none once an
+/// argument has been started.
+pub fn command_suggestions(input: &str) -> Vec<&'static str> {
+    let input = input.trim_start();
+    if input.contains(char::is_whitespace) {
+        return Vec::new();
+    }
+    let mut names: Vec<&str> = COMMANDS
+        .iter()
+        .copied()
+        .chain(ALL_ACTIONS.iter().map(|action| action.name()))
+        .filter(|name| name.starts_with(input))
+        .collect();
+    names.sort_unstable();
+    names.dedup();
+    names
+}
+
+/// `input` with the command name extended as far as every suggestion
+/// agrees, followed by a space once only one command is left.
+pub fn complete_command(input: &str) -> Option<String> {
+    match command_suggestions(input).as_slice() {
+        [] => None,
+        [name] => Some(format!("{} ", name)),
+        names => {
+            let common = common_prefix_len(names);
+            (common > input.trim_start().len()).then(|| names[0][..common].to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_palette_commands() {
+        assert_eq!(
+            parse_command("sort qual desc"),
+            Ok(Command::Sort(Column::Qual, true))
+        );
+        assert_eq!(
+            parse_command(" filter chrom chr1,chr2 "),
+            Ok(Command::Filter(ModalKind::Chrom, "chr1,chr2".to_string()))
+        );
+        assert_eq!(
+            parse_command("export out.vcf"),
+            Ok(Command::Export(Some("out.vcf".into())))
+        );
+        assert_eq!(parse_command("stats"), Ok(Command::Tab(2)));
+        assert_eq!(
+            parse_command("group SVTYPE"),
+            Ok(Command::Group(Some("SVTYPE".to_string())))
+        );
+        assert_eq!(parse_command("group"), Ok(Command::Group(None)));
+        assert_eq!(parse_command("head 500"), Ok(Command::Head(Some(500))));
+        assert!(parse_command("head 0").is_err());
+        assert_eq!(
+            parse_command("toggle_legend"),
+            Ok(Command::Action(Action::ToggleLegend))
+        );
+        assert!(parse_command("sort nope").is_err());
+        assert!(parse_command("frobnicate").is_err());
+    }
+
+    #[test]
+    fn completes_command_names() {
+        assert_eq!(complete_command("exp").as_deref(), Some("export "));
+        assert_eq!(
+            complete_command("toggle_r").as_deref(),
+            Some("toggle_relative_pos ")
+        );
+        assert_eq!(complete_command("tog").as_deref(), Some("toggle_"));
+        assert_eq!(complete_command("sort"), None);
+        assert!(command_suggestions("sort q").is_empty());
+    }
+}
@@ -0,0 +1,590 @@
+//! Record filters, kept apart from `App` so they can be built and tested
+//! without any UI state.
+
+use std::ops::Range;
+
+use crate::{
+    app::{Column, MENU_ITEMS, MenuItem},
+    vcf::{BedRegions, PosRange, VcfRecord, parse_comparison, parse_info, parse_pos_range},
+};
+
+/// Every filter the VCF tab applies. Empty strings, `IdPresence::Any` and
+/// `BedMode::Off` pass everything.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct FilterSet {
+    pub chrom: String,
+    pub ref_: String,
+    pub alt: String,
+    pub pos: String,              // e.g. "1000-5000" or "12345"
+    pub indel: String,            // e.g. ">=5"
+    pub svtype: String,           // e.g. "DEL,DUP"
+    pub info: String,             // e.g. "DP>=10", "DB" or "GENE=BRCA1"
+    pub flag: String,             // INFO flag keys, e.g. "SOMATIC" or "DB,H2"
+    pub non_missing: Vec<Column>, // columns that may not be `.`
+    pub id: String,
+    pub id_presence: IdPresence,
+    pub bed: Option<BedRegions>,
+    pub bed_mode: BedMode,
+    pub multiallelic_only: bool,
+    pub case_sensitive: bool,
+}
+
+impl FilterSet {
+    pub fn pos_range(&self) -> PosRange {
+        parse_pos_range(&self.pos)
+    }
+
+    /// Sets CHROM and POS from an IGV-style region such as
+    /// `chr1:1,000-5,000` (thousands separators allowed); a bare `chr1`
+    /// clears POS and an empty region clears both. Returns false, changing
+    /// nothing, if the coordinates don't parse.
+    pub fn set_region(&mut self, region: &str) -> bool {
+        let region = region.trim();
+        let (chrom, pos) = match region.rsplit_once(':') {
+            Some((chrom, coords)) => (chrom, coords.replace(',', "")),
+            None => (region, String::new()),
+        };
+        let pos = pos.trim();
+        if (chrom.is_empty() && !region.is_empty())
+            || (!pos.is_empty() && parse_pos_range(pos) == PosRange::None)
+        {
+            return false;
+        }
+        self.chrom = chrom.to_string();
+        self.pos = pos.to_string();
+        true
+    }
+
+    /// A POS filter was entered but doesn't parse (e.g. `5000-1000`), so it
+    /// is being ignored.
+    pub fn pos_invalid(&self) -> bool {
+        !self.pos.trim().is_empty() && self.pos_range() == PosRange::None
+    }
+
+    /// Non-empty filters as (name, value) pairs, in menu order.
+    pub fn active(&self) -> Vec<(&'static str, String)> {
+        MENU_ITEMS
+            .iter()
+            .filter_map(|&item| Some((item.label(), self.value(item)?)))
+            .collect()
+    }
+
+    /// Current value of an active filter, e.g. for the filter menu.
+    pub fn value(&self, item: MenuItem) -> Option<String> {
+        let value = match item {
+            MenuItem::Chrom => self.chrom.clone(),
+            MenuItem::Ref => self.ref_.clone(),
+            MenuItem::Alt => self.alt.clone(),
+            MenuItem::Pos => self.pos.clone(),
+            MenuItem::Id => self.id.clone(),
+            MenuItem::NovelOnly if self.id_presence == IdPresence::Novel => "on".to_string(),
+            MenuItem::KnownOnly if self.id_presence == IdPresence::Known => "on".to_string(),
+            MenuItem::IndelLen => self.indel.clone(),
+            MenuItem::SvType => self.svtype.clone(),
+            MenuItem::Info => self.info.clone(),
+            MenuItem::Flag => self.flag.clone(),
+            MenuItem::MultiAllelic if self.multiallelic_only => "on".to_string(),
+            MenuItem::NonMissing => {
+                let labels: Vec<&str> = self.non_missing.iter().map(|c| c.label()).collect();
+                labels.join(",")
+            }
+            MenuItem::Bed if self.bed_mode != BedMode::Off => self.bed_mode.label().to_string(),
+            _ => String::new(),
+        };
+        (!value.is_empty()).then_some(value)
+    }
+
+    /// Whether `r` passes every filter. `pos_range` is `pos_range()`, parsed
+    /// once by the caller rather than per record.
+    pub fn matches(&self, r: &VcfRecord, pos_range: &PosRange) -> bool {
+        MENU_ITEMS
+            .iter()
+            .all(|&item| self.passes(item, r, pos_range))
+    }
+
+    /// Whether `r` passes the single filter behind `item`; inactive filters
+    /// pass everything.
+    pub fn passes(&self, item: MenuItem, r: &VcfRecord, pos_range: &PosRange) -> bool {
+        match item {
+            MenuItem::Chrom => list_matches(&r.chrom, &self.chrom, self.case_sensitive),
+            MenuItem::Ref => text_matches(&r.ref_, &self.ref_, self.case_sensitive),
+            MenuItem::Alt => text_matches(&r.alt, &self.alt, self.case_sensitive),
+            MenuItem::Id => text_matches(&r.id, &self.id, self.case_sensitive),
+            MenuItem::NovelOnly | MenuItem::KnownOnly => self.id_presence.matches(&r.id),
+            MenuItem::Pos => match *pos_range {
+                PosRange::None => true,
+                PosRange::Exact(pos) => r.pos == pos,
+                PosRange::Range(start, end) => r.pos >= start && r.pos <= end,
+            },
+            MenuItem::Bed => match (&self.bed, self.bed_mode) {
+                (Some(bed), BedMode::Inside | BedMode::Outside) => {
+                    let inside = bed.contains(&r.chrom, r.pos);
+                    inside == (self.bed_mode == BedMode::Inside)
+                }
+                _ => true,
+            },
+            MenuItem::SvType => list_matches(
+                r.sv_type().unwrap_or_default(),
+                &self.svtype,
+                self.case_sensitive,
+            ),
+            MenuItem::Info => info_matches(r, &self.info, self.case_sensitive),
+            MenuItem::Flag => flag_matches(r, &self.flag, self.case_sensitive),
+            MenuItem::MultiAllelic => !self.multiallelic_only || r.is_multiallelic(),
+            MenuItem::NonMissing => self.non_missing.iter().all(|&c| !field_missing(r, c)),
+            MenuItem::IndelLen => match parse_comparison(&self.indel) {
+                Some(cmp) => cmp.matches(r.indel_length() as f64),
+                None => true,
+            },
+            // Sets CHROM and POS rather than filtering on its own.
+            MenuItem::Region | MenuItem::ClearAll | MenuItem::Cancel => true,
+        }
+    }
+}
+
+/// Records from `records` that pass every filter in `filters`, in order.
+pub fn apply_filters<'a>(records: &'a [VcfRecord], filters: &FilterSet) -> Vec<&'a VcfRecord> {
+    let pos_range = filters.pos_range();
+    records
+        .iter()
+        .filter(|r| filters.matches(r, &pos_range))
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BedMode {
+    #[default]
+    Off,
+    Inside,
+    Outside,
+}
+
+impl BedMode {
+    pub fn next(self) -> Self {
+        match self {
+            BedMode::Off => BedMode::Inside,
+            BedMode::Inside => BedMode::Outside,
+            BedMode::Outside => BedMode::Off,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            BedMode::Off => "off",
+            BedMode::Inside => "inside",
+            BedMode::Outside => "outside",
+        }
+    }
+}
+
+/// Whether records must carry an ID: novel calls have `.`, known ones
+/// (e.g. dbSNP rsIDs) anything else.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IdPresence {
+    #[default]
+    Any,
+    Novel,
+    Known,
+}
+
+impl IdPresence {
+    pub fn matches(self, id: &str) -> bool {
+        match self {
+            IdPresence::Any => true,
+            IdPresence::Novel => id == ".",
+            IdPresence::Known => id != ".",
+        }
+    }
+}
+
+/// Splits a leading `!` off a filter value. `!!` escapes a literal `!`.
+fn split_negation(filter: &str) -> (bool, &str) {
+    if filter.starts_with("!!") {
+        (false, &filter[1..])
+    } else if let Some(rest) = filter.strip_prefix('!') {
+        (true, rest)
+    } else {
+        (false, filter)
+    }
+}
+
+fn contains_text(value: &str, pattern: &str, case_sensitive: bool) -> bool {
+    if case_sensitive {
+        value.contains(pattern)
+    } else {
+        value.to_lowercase().contains(&pattern.to_lowercase())
+    }
+}
+
+fn text_matches(value: &str, filter: &str, case_sensitive: bool) -> bool {
+    let (negate, pattern) = split_negation(filter);
+    if pattern.is_empty() {
+        return true;
+    }
+    contains_text(value, pattern, case_sensitive) != negate
+}
+
+/// Like `text_matches`, but `filter` is a comma-separated list and any entry
+/// may match. A leading `!` negates the whole list.
+fn list_matches(value: &str, filter: &str, case_sensitive: bool) -> bool {
+    let (negate, pattern) = split_negation(filter);
+    let mut entries = pattern
+        .split(',')
+        .map(str::trim)
+        .filter(|e| !e.is_empty())
+        .peekable();
+    if entries.peek().is_none() {
+        return true;
+    }
+    entries.any(|e| contains_text(value, e, case_sensitive)) != negate
+}
+
+/// Whether `column` holds the VCF missing value `.` (or nothing) in `r`.
+fn field_missing(r: &VcfRecord, column: Column) -> bool {
+    let value = match column {
+        Column::Id => &r.id,
+        Column::Ref => &r.ref_,
+        Column::Alt => &r.alt,
+        Column::Qual => return r.qual.is_none(),
+        Column::Filter => &r.filter,
+        Column::Info => &r.info,
+        Column::Line | Column::Chrom | Column::Pos | Column::Source => return false,
+    };
+    value.is_empty() || value == "."
+}
+
+/// Splits an INFO filter into its key and the test after it, e.g.
+/// `DP>=10` into `DP` and `>=10`.
+fn split_info_filter(expr: &str) -> (&str, &str) {
+    let at = expr.find(['=', '<', '>', '!']).unwrap_or(expr.len());
+    (expr[..at].trim(), &expr[at..])
+}
+
+/// INFO filter: `KEY` keeps records carrying the key, `KEY>=10` compares
+/// its first numeric value and `KEY=text`/`KEY!=text` match the value as
+/// text. A leading `!` inverts the result.
+fn info_matches(r: &VcfRecord, filter: &str, case_sensitive: bool) -> bool {
+    let (negate, expr) = split_negation(filter.trim());
+    if expr.is_empty() {
+        return true;
+    }
+    let (key, test) = split_info_filter(expr);
+    let hit = match r.info_value(key) {
+        None => false,
+        Some(_) if test.is_empty() => true,
+        Some(value) => match parse_comparison(test) {
+            Some(cmp) => value
+                .split(',')
+                .next()
+                .and_then(|v| v.parse::<f64>().ok())
+                .is_some_and(|x| cmp.matches(x)),
+            None => match test.strip_prefix("!=") {
+                Some(text) => !contains_text(value, text.trim(), case_sensitive),
+                None => contains_text(value, test.trim_start_matches('=').trim(), case_sensitive),
+            },
+        },
+    };
+    hit != negate
+}
+
+/// Whether INFO carries one of the comma-separated keys in `filter` as a
+/// flag, i.e. without a value. A leading `!` negates the whole list.
+fn flag_matches(r: &VcfRecord, filter: &str, case_sensitive: bool) -> bool {
+    let (negate, pattern) = split_negation(filter.trim());
+    let keys: Vec<&str> = pattern
+        .split(',')
+        .map(str::trim)
+        .filter(|k| !k.is_empty())
+        .collect();
+    if keys.is_empty() {
+        return true;
+    }
+    let hit = parse_info(&r.info).iter().any(|&(key, value)| {
+        value.is_none()
+            && keys.iter().any(|k| {
+                if case_sensitive {
+                    key == *k
+                } else {
+                    key.eq_ignore_ascii_case(k)
+                }
+            })
+    });
+    hit != negate
+}
+
+/// INFO keys from `keys` that complete the key being typed in `input`;
+/// none once the key is followed by a test.
+pub fn info_suggestions<'a>(keys: &'a [String], input: &str) -> Vec<&'a str> {
+    let (_, expr) = split_negation(input.trim_start());
+    let (key, test) = split_info_filter(expr);
+    if !test.is_empty() {
+        return Vec::new();
+    }
+    keys.iter()
+        .map(String::as_str)
+        .filter(|k| k.starts_with(key))
+        .collect()
+}
+
+/// `input` with its key extended to the longest prefix shared by every
+/// suggestion, or `None` if that adds nothing.
+pub fn complete_info_key(keys: &[String], input: &str) -> Option<String> {
+    let suggestions = info_suggestions(keys, input);
+    let first = suggestions.first()?;
+    let common = common_prefix_len(&suggestions);
+    let (negate, expr) = split_negation(input.trim_start());
+    let key = split_info_filter(expr).0;
+    (common > key.chars().count()).then(|| {
+        let bang = if negate { "!" } else { "" };
+        format!("{}{}", bang, first.chars().take(common).collect::<String>())
+    })
+}
+
+/// Length in characters of the prefix shared by all of `words`.
+pub fn common_prefix_len(words: &[&str]) -> usize {
+    let Some(first) = words.first() else {
+        return 0;
+    };
+    words.iter().fold(first.chars().count(), |len, word| {
+        first
+            .chars()
+            .zip(word.chars())
+            .take(len)
+            .take_while(|(a, b)| a == b)
+            .count()
+    })
+}
+
+/// Byte range of the first occurrence of `pattern` in `value`.
+fn find_text(value: &str, pattern: &str, case_sensitive: bool) -> Option<Range<usize>> {
+    let start = if case_sensitive {
+        value.find(pattern)?
+    } else {
+        // Lowercasing can change byte lengths outside ASCII; only trust the
+        // offset when it didn't.
+        let lower = value.to_lowercase();
+        if lower.len() != value.len() {
+            return None;
+        }
+        let needle = pattern.to_lowercase();
+        let start = lower.find(&needle)?;
+        return Some(start..start + needle.len());
+    };
+    Some(start..start + pattern.len())
+}
+
+/// The part of `value` that made it pass `filter`, for highlighting.
+/// Negated and empty filters highlight nothing. With `list`, the filter is
+/// comma-separated as in `list_matches`.
+pub fn filter_match(
+    value: &str,
+    filter: &str,
+    case_sensitive: bool,
+    list: bool,
+) -> Option<Range<usize>> {
+    let (negate, pattern) = split_negation(filter);
+    if negate {
+        return None;
+    }
+    if list {
+        pattern
+            .split(',')
+            .map(str::trim)
+            .filter(|e| !e.is_empty())
+            .find_map(|e| find_text(value, e, case_sensitive))
+    } else if pattern.is_empty() {
+        None
+    } else {
+        find_text(value, pattern, case_sensitive)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vcf::parse_vcf_reader;
+    use std::io::Cursor;
+
+    fn records() -> Vec<VcfRecord> {
+        let data = "1\t100\t.\tA\tG\t.\t.\t.\n\
+                    1\t200\t.\tC\tT\t.\t.\t.\n\
+                    10\t150\t.\tA\tAT\t.\t.\t.\n\
+                    X\t300\t.\tG\tA\t.\t.\t.\n";
+        parse_vcf_reader(Cursor::new(data)).unwrap().records
+    }
+
+    fn positions(records: &[VcfRecord], filters: &FilterSet) -> Vec<(String, u64)> {
+        apply_filters(records, filters)
+            .iter()
+            .map(|r| (r.chrom.clone(), r.pos))
+            .collect()
+    }
+
+    #[test]
+    fn empty_filters_pass_everything() {
+        let records = records();
+        assert_eq!(apply_filters(&records, &FilterSet::default()).len(), 4);
+    }
+
+    #[test]
+    fn chrom_filter_matches_substrings() {
+        let records = records();
+        let filters = FilterSet {
+            chrom: "1".into(),
+            ..Default::default()
+        };
+        let chroms: Vec<String> = positions(&records, &filters)
+            .into_iter()
+            .map(|(c, _)| c)
+            .collect();
+        assert_eq!(chroms, ["1", "1", "10"]);
+    }
+
+    #[test]
+    fn pos_filter_takes_exact_values_and_ranges() {
+        let records = records();
+        let exact = FilterSet {
+            pos: "200".into(),
+            ..Default::default()
+        };
+        assert_eq!(positions(&records, &exact), [("1".into(), 200)]);
+
+        let range = FilterSet {
+            pos: "100-200".into(),
+            ..Default::default()
+        };
+        let hits: Vec<u64> = positions(&records, &range)
+            .into_iter()
+            .map(|(_, p)| p)
+            .collect();
+        assert_eq!(hits, [100, 200, 150]);
+    }
+
+    #[test]
+    fn unparsable_pos_filter_is_flagged() {
+        for (pos, invalid) in [
+            ("", false),
+            ("100-200", false),
+            ("5000-1000", true),
+            ("abc-def", true),
+        ] {
+            let filters = FilterSet {
+                pos: pos.into(),
+                ..Default::default()
+            };
+            assert_eq!(filters.pos_invalid(), invalid, "{pos}");
+        }
+    }
+
+    #[test]
+    fn region_sets_chrom_and_pos() {
+        let mut filters = FilterSet::default();
+        assert!(filters.set_region("chr1:1,000-5,000"));
+        assert_eq!(
+            (filters.chrom.as_str(), filters.pos.as_str()),
+            ("chr1", "1000-5000")
+        );
+        assert!(filters.set_region("HLA-A*01:01:1000"));
+        assert_eq!(
+            (filters.chrom.as_str(), filters.pos.as_str()),
+            ("HLA-A*01:01", "1000")
+        );
+        assert!(filters.set_region("chrX"));
+        assert_eq!((filters.chrom.as_str(), filters.pos.as_str()), ("chrX", ""));
+
+        assert!(!filters.set_region("chr2:5,000-1,000"));
+        assert!(!filters.set_region(":100"));
+        assert_eq!(filters.chrom, "chrX");
+        assert!(filters.set_region(""));
+        assert!(filters.chrom.is_empty());
+    }
+
+    #[test]
+    fn filters_combine() {
+        let records = records();
+        let filters = FilterSet {
+            chrom: "1".into(),
+            pos: "120-400".into(),
+            alt: "T".into(),
+            ..Default::default()
+        };
+        assert_eq!(
+            positions(&records, &filters),
+            [("1".into(), 200), ("10".into(), 150)]
+        );
+    }
+
+    #[test]
+    fn info_filter_checks_keys_numbers_and_text() {
+        let data = "1\t10\t.\tA\tG\t.\t.\tDP=5;DB\n\
+                    1\t20\t.\tA\tG\t.\t.\tDP=30;GENE=BRCA1\n\
+                    1\t30\t.\tA\tG\t.\t.\t.\n";
+        let records = parse_vcf_reader(Cursor::new(data)).unwrap().records;
+        let hits = |info: &str| {
+            let filters = FilterSet {
+                info: info.into(),
+                ..Default::default()
+            };
+            positions(&records, &filters)
+                .into_iter()
+                .map(|(_, pos)| pos)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(hits("DB"), [10]);
+        assert_eq!(hits("!DB"), [20, 30]);
+        assert_eq!(hits("DP>=10"), [20]);
+        assert_eq!(hits("GENE=brca"), [20]);
+    }
+
+    #[test]
+    fn flag_filter_needs_a_valueless_key() {
+        let data = "1\t10\t.\tA\tG\t.\t.\tDP=5;DB\n\
+                    1\t20\t.\tA\tG\t.\t.\tSOMATIC=1\n\
+                    1\t30\t.\tA\tG\t.\t.\tsomatic\n";
+        let records = parse_vcf_reader(Cursor::new(data)).unwrap().records;
+        let hits = |flag: &str| {
+            let filters = FilterSet {
+                flag: flag.into(),
+                ..Default::default()
+            };
+            positions(&records, &filters)
+                .into_iter()
+                .map(|(_, pos)| pos)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(hits("DB"), [10]);
+        assert_eq!(hits("SOMATIC"), [30]);
+        assert_eq!(hits("DB,SOMATIC"), [10, 30]);
+        assert_eq!(hits("!DB"), [20, 30]);
+    }
+
+    #[test]
+    fn filter_match_spans_the_text_searched_for() {
+        assert_eq!(filter_match("rs123", "S12", false, false), Some(1..4));
+        // The Kelvin sign is three bytes but lowercases to a one-byte `k`.
+        assert_eq!(filter_match("k1", "\u{212a}", false, false), Some(0..1));
+        assert_eq!(filter_match("k1", "\u{212a}", true, false), None);
+    }
+
+    #[test]
+    fn non_missing_drops_dot_fields() {
+        let data = "1\t10\trs1\tA\tG\t30\t.\t.\n\
+                    1\t20\t.\tA\tG\t30\t.\t.\n\
+                    1\t30\trs3\tA\tG\t.\t.\t.\n";
+        let records = parse_vcf_reader(Cursor::new(data)).unwrap().records;
+        let filters = FilterSet {
+            non_missing: vec![Column::Id, Column::Qual],
+            ..Default::default()
+        };
+        assert_eq!(positions(&records, &filters), [("1".into(), 10)]);
+    }
+
+    #[test]
+    fn info_keys_complete_to_common_prefix() {
+        let keys: Vec<String> = ["AC", "AF", "AN", "DP"].map(String::from).to_vec();
+        assert_eq!(info_suggestions(&keys, "A"), ["AC", "AF", "AN"]);
+        assert_eq!(info_suggestions(&keys, "DP>"), Vec::<&str>::new());
+        assert_eq!(complete_info_key(&keys, "!D").as_deref(), Some("!DP"));
+        assert_eq!(complete_info_key(&keys, "A"), None);
+    }
+}
@@ -0,0 +1,993 @@
+//! Key bindings and the per-tab key handlers.
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::{
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    app::{
+        ALL_COLUMNS, App, Config, EXPORT_ITEMS, ExportItem, MENU_ITEMS, MenuItem, ModalKind,
+        ModalState,
+    },
+    command::{Command, complete_command, parse_command},
+    filter::{IdPresence, complete_info_key},
+};
+
+/// Above this many records the live match count in filter inputs waits for
+/// a pause in typing instead of rescanning on every key.
+const LIVE_COUNT_LIMIT: usize = 200_000;
+const LIVE_COUNT_DELAY: Duration = Duration::from_millis(250);
+
+/// How long the first key of a chord such as `gg` waits for the second.
+const CHORD_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Quit,
+    Back,
+    MoveUp,
+    MoveDown,
+    MoveTop,
+    MoveBottom,
+    Open,
+    NextTab,
+    PrevTab,
+    OpenFilter,
+    ResetFilters,
+    OpenColumns,
+    FocusDetail,
+    ToggleDimmed,
+    ToggleCase,
+    ToggleBed,
+    ToggleZeroBased,
+    ToggleRelativePos,
+    ToggleLegend,
+    SortNext,
+    SortReverse,
+    SortRecords,
+    WriteReport,
+    ToggleBookmark,
+    OpenExport,
+    OpenAll,
+    NextChrom,
+    PrevChrom,
+    ScrollLeft,
+    ScrollRight,
+    CommandPalette,
+    ToggleWrap,
+    ToggleTypeColors,
+    OpenRecent,
+    Reload,
+    GroupBy,
+    CopyFiltered,
+    NarrowFilters,
+    WidenFilters,
+    ToggleHead,
+    ToggleAlleles,
+    OpenEditor,
+    ShowRecord,
+}
+
+pub const ALL_ACTIONS: &[Action] = &[
+    Action::Quit,
+    Action::Back,
+    Action::MoveUp,
+    Action::MoveDown,
+    Action::MoveTop,
+    Action::MoveBottom,
+    Action::Open,
+    Action::NextTab,
+    Action::PrevTab,
+    Action::OpenFilter,
+    Action::ResetFilters,
+    Action::OpenColumns,
+    Action::FocusDetail,
+    Action::ToggleDimmed,
+    Action::ToggleCase,
+    Action::ToggleBed,
+    Action::ToggleZeroBased,
+    Action::ToggleRelativePos,
+    Action::ToggleLegend,
+    Action::SortNext,
+    Action::SortReverse,
+    Action::SortRecords,
+    Action::WriteReport,
+    Action::ToggleBookmark,
+    Action::OpenExport,
+    Action::OpenAll,
+    Action::NextChrom,
+    Action::PrevChrom,
+    Action::ScrollLeft,
+    Action::ScrollRight,
+    Action::CommandPalette,
+    Action::ToggleWrap,
+    Action::ToggleTypeColors,
+    Action::OpenRecent,
+    Action::Reload,
+    Action::GroupBy,
+    Action::CopyFiltered,
+    Action::NarrowFilters,
+    Action::WidenFilters,
+    Action::ToggleHead,
+    Action::ToggleAlleles,
+    Action::OpenEditor,
+    Action::ShowRecord,
+];
+
+impl Action {
+    /// Name used for `key.<name>` entries in the config file.
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::Back => "back",
+            Action::MoveUp => "move_up",
+            Action::MoveDown => "move_down",
+            Action::MoveTop => "move_top",
+            Action::MoveBottom => "move_bottom",
+            Action::Open => "open",
+            Action::NextTab => "next_tab",
+            Action::PrevTab => "prev_tab",
+            Action::OpenFilter => "open_filter",
+            Action::ResetFilters => "reset_filters",
+            Action::OpenColumns => "open_columns",
+            Action::FocusDetail => "focus_detail",
+            Action::ToggleDimmed => "toggle_dimmed",
+            Action::ToggleCase => "toggle_case",
+            Action::ToggleBed => "toggle_bed",
+            Action::ToggleZeroBased => "toggle_zero_based",
+            Action::ToggleRelativePos => "toggle_relative_pos",
+            Action::ToggleLegend => "toggle_legend",
+            Action::SortNext => "sort_next",
+            Action::SortReverse => "sort_reverse",
+            Action::SortRecords => "sort_records",
+            Action::WriteReport => "write_report",
+            Action::ToggleBookmark => "toggle_bookmark",
+            Action::OpenExport => "open_export",
+            Action::OpenAll => "open_all",
+            Action::NextChrom => "next_chrom",
+            Action::PrevChrom => "prev_chrom",
+            Action::ScrollLeft => "scroll_left",
+            Action::ScrollRight => "scroll_right",
+            Action::CommandPalette => "command_palette",
+            Action::ToggleWrap => "toggle_wrap",
+            Action::ToggleTypeColors => "toggle_type_colors",
+            Action::OpenRecent => "open_recent",
+            Action::Reload => "reload",
+            Action::GroupBy => "group_by",
+            Action::CopyFiltered => "copy_filtered",
+            Action::NarrowFilters => "narrow_filters",
+            Action::WidenFilters => "widen_filters",
+            Action::ToggleHead => "toggle_head",
+            Action::ToggleAlleles => "toggle_alleles",
+            Action::OpenEditor => "open_editor",
+            Action::ShowRecord => "show_record",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyBinding {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyBinding {
+    fn from_event(key: KeyEvent) -> Self {
+        // Shift is already reflected in the character itself ('G' vs 'g').
+        let modifiers = match key.code {
+            KeyCode::Char(_) => key.modifiers - KeyModifiers::SHIFT,
+            _ => key.modifiers,
+        };
+        KeyBinding {
+            code: key.code,
+            modifiers,
+        }
+    }
+
+    /// Parses specs like `j`, `G`, `Up`, `PageDown`, `Space`, `C-d` or
+    /// `S-Left`.
+    pub fn parse(spec: &str) -> Option<Self> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut name = spec.trim();
+        loop {
+            let (modifier, rest) = match name.split_at_checked(2) {
+                Some(("C-", rest)) if !rest.is_empty() => (KeyModifiers::CONTROL, rest),
+                Some(("S-", rest)) if !rest.is_empty() => (KeyModifiers::SHIFT, rest),
+                _ => break,
+            };
+            modifiers |= modifier;
+            name = rest;
+        }
+        let code = match name {
+            "Up" => KeyCode::Up,
+            "Down" => KeyCode::Down,
+            "Left" => KeyCode::Left,
+            "Right" => KeyCode::Right,
+            "Home" => KeyCode::Home,
+            "End" => KeyCode::End,
+            "PageUp" => KeyCode::PageUp,
+            "PageDown" => KeyCode::PageDown,
+            "Enter" => KeyCode::Enter,
+            "Esc" => KeyCode::Esc,
+            "Tab" => KeyCode::Tab,
+            "BackTab" => KeyCode::BackTab,
+            "Backspace" => KeyCode::Backspace,
+            "Delete" => KeyCode::Delete,
+            "Space" => KeyCode::Char(' '),
+            _ => {
+                let mut chars = name.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => KeyCode::Char(c),
+                    _ => return None,
+                }
+            }
+        };
+        Some(KeyBinding { code, modifiers })
+    }
+
+    /// Parses a key or a two-key chord written with a space, e.g. `g g`.
+    fn parse_sequence(spec: &str) -> Option<Vec<Self>> {
+        let keys: Vec<Self> = spec
+            .split_whitespace()
+            .map(KeyBinding::parse)
+            .collect::<Option<_>>()?;
+        (1..=2).contains(&keys.len()).then_some(keys)
+    }
+
+    pub fn label(&self) -> String {
+        let name = match self.code {
+            KeyCode::Char(' ') => "Space".to_string(),
+            KeyCode::Char(c) => c.to_string(),
+            KeyCode::BackTab => "BackTab".to_string(),
+            KeyCode::PageUp => "PageUp".to_string(),
+            KeyCode::PageDown => "PageDown".to_string(),
+            code => format!("{:?}", code),
+        };
+        let name = if self.modifiers.contains(KeyModifiers::SHIFT) {
+            format!("S-{}", name)
+        } else {
+            name
+        };
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            format!("C-{}", name)
+        } else {
+            name
+        }
+    }
+}
+
+/// Maps keys to actions for the non-modal views. Built from a preset
+/// (`keys = default|arrows|vim` in the config file) with per-action
+/// overrides such as `key.move_down = j, Down` or `key.move_top = g g`.
+#[derive(Debug, Clone)]
+pub struct KeyMap {
+    bindings: Vec<(Vec<KeyBinding>, Action)>, // one key or a two-key chord
+}
+
+/// What a key sequence means to the key map.
+enum Lookup {
+    Action(Action),
+    Prefix, // the start of a chord; wait for the next key
+    Unbound,
+}
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        KeyMap::preset("default").unwrap_or(KeyMap {
+            bindings: Vec::new(),
+        })
+    }
+}
+
+impl KeyMap {
+    fn preset(name: &str) -> Option<Self> {
+        let mut specs: Vec<(&str, Action)> = vec![
+            ("q", Action::Quit),
+            ("Esc", Action::Back),
+            ("Up", Action::MoveUp),
+            ("Down", Action::MoveDown),
+            ("Home", Action::MoveTop),
+            ("End", Action::MoveBottom),
+            ("Enter", Action::Open),
+            ("Space", Action::ShowRecord),
+            ("Tab", Action::NextTab),
+            ("BackTab", Action::PrevTab),
+            ("f", Action::OpenFilter),
+            ("x", Action::ResetFilters),
+            ("v", Action::OpenColumns),
+            ("i", Action::FocusDetail),
+            ("c", Action::ToggleCase),
+            ("b", Action::ToggleBed),
+            ("z", Action::ToggleZeroBased),
+            ("p", Action::ToggleRelativePos),
+            ("L", Action::ToggleLegend),
+            ("d d", Action::ResetFilters),
+            ("s", Action::SortNext),
+            ("S", Action::SortReverse),
+            ("O", Action::SortRecords),
+            ("r", Action::WriteReport),
+            ("m", Action::ToggleBookmark),
+            ("e", Action::OpenExport),
+            ("C-a", Action::OpenAll),
+            ("}", Action::NextChrom),
+            ("{", Action::PrevChrom),
+            ("S-Left", Action::ScrollLeft),
+            ("S-Right", Action::ScrollRight),
+            (":", Action::CommandPalette),
+            ("w", Action::ToggleWrap),
+            ("t", Action::ToggleTypeColors),
+            ("C-r", Action::OpenRecent),
+            ("R", Action::Reload),
+            ("a", Action::GroupBy),
+            ("Y", Action::CopyFiltered),
+            ("<", Action::NarrowFilters),
+            (">", Action::WidenFilters),
+            ("N", Action::ToggleHead),
+            ("A", Action::ToggleAlleles),
+            ("E", Action::OpenEditor),
+        ];
+        match name {
+            "default" => specs.extend([
+                ("k", Action::MoveUp),
+                ("j", Action::MoveDown),
+                ("g g", Action::MoveTop),
+                ("G", Action::MoveBottom),
+                ("h", Action::ToggleDimmed),
+            ]),
+            "arrows" => specs.extend([
+                ("Left", Action::PrevTab),
+                ("Right", Action::NextTab),
+                ("h", Action::ToggleDimmed),
+            ]),
+            "vim" => specs.extend([
+                ("k", Action::MoveUp),
+                ("j", Action::MoveDown),
+                ("h", Action::PrevTab),
+                ("l", Action::NextTab),
+                ("g g", Action::MoveTop),
+                ("G", Action::MoveBottom),
+                ("H", Action::ToggleDimmed),
+            ]),
+            _ => return None,
+        }
+        let bindings = specs
+            .into_iter()
+            .filter_map(|(spec, action)| Some((KeyBinding::parse_sequence(spec)?, action)))
+            .collect();
+        Some(KeyMap { bindings })
+    }
+
+    pub fn from_config(config: &Config) -> Self {
+        let mut keymap = config
+            .get("keys")
+            .and_then(KeyMap::preset)
+            .unwrap_or_default();
+        for &action in ALL_ACTIONS {
+            let Some(specs) = config.get(&format!("key.{}", action.name())) else {
+                continue;
+            };
+            let keys: Vec<Vec<KeyBinding>> = specs
+                .split(',')
+                .filter_map(KeyBinding::parse_sequence)
+                .collect();
+            keymap
+                .bindings
+                .retain(|(k, a)| *a != action && !keys.contains(k));
+            keymap
+                .bindings
+                .extend(keys.into_iter().map(|k| (k, action)));
+        }
+        keymap
+    }
+
+    /// Action bound to `key` on its own; chords are ignored.
+    fn action(&self, key: KeyEvent) -> Option<Action> {
+        match self.lookup(&[KeyBinding::from_event(key)]) {
+            Lookup::Action(action) => Some(action),
+            _ => None,
+        }
+    }
+
+    fn lookup(&self, keys: &[KeyBinding]) -> Lookup {
+        if let Some((_, action)) = self.bindings.iter().find(|(k, _)| k == keys) {
+            return Lookup::Action(*action);
+        }
+        if self
+            .bindings
+            .iter()
+            .any(|(k, _)| k.len() > keys.len() && k.starts_with(keys))
+        {
+            Lookup::Prefix
+        } else {
+            Lookup::Unbound
+        }
+    }
+
+    /// Label of the first key bound to `action`, for on-screen hints.
+    pub fn hint(&self, action: Action) -> String {
+        self.bindings
+            .iter()
+            .find(|(_, a)| *a == action)
+            .map(|(keys, _)| keys.iter().map(KeyBinding::label).collect())
+            .unwrap_or_else(|| "unbound".to_string())
+    }
+}
+
+/// Resolves `key` to an action, holding the first key of a chord on `App`
+/// until the second arrives. A key that doesn't complete the chord is
+/// looked up on its own instead.
+fn chord_action(app: &mut App, key: KeyEvent) -> Option<Action> {
+    let binding = KeyBinding::from_event(key);
+    let pending = app
+        .pending_key
+        .take()
+        .filter(|(_, at)| at.elapsed() <= CHORD_TIMEOUT);
+    if let Some((first, _)) = pending
+        && let Lookup::Action(action) = app.keymap.lookup(&[first, binding])
+    {
+        return Some(action);
+    }
+    match app.keymap.lookup(&[binding]) {
+        Lookup::Action(action) => Some(action),
+        Lookup::Prefix => {
+            app.pending_key = Some((binding, Instant::now()));
+            None
+        }
+        Lookup::Unbound => None,
+    }
+}
+
+/// Moves a list selection one step, optionally wrapping at either end.
+/// Moving down from no selection selects the first row.
+fn step_selection(selected: Option<usize>, len: usize, down: bool, wrap: bool) -> Option<usize> {
+    if len == 0 {
+        return None;
+    }
+    let Some(sel) = selected else {
+        return down.then_some(0);
+    };
+    let next = match (down, wrap) {
+        (true, _) if sel + 1 < len => sel + 1,
+        (true, true) => 0,
+        (false, _) if sel > 0 => sel - 1,
+        (false, true) => len - 1,
+        _ => sel,
+    };
+    Some(next)
+}
+
+/// While an export runs, Back cancels it on any tab; returns whether the
+/// key was used.
+pub fn handle_export_key(app: &mut App, key: crossterm::event::KeyEvent) -> bool {
+    if app.export.is_some() && app.keymap.action(key) == Some(Action::Back) {
+        app.cancel_export();
+        return true;
+    }
+    false
+}
+
+pub fn handle_files_tab(app: &mut App, key: crossterm::event::KeyEvent) {
+    // Plain characters edit the filter, so only Quit, Ctrl chords and
+    // non-character keys are looked up in the keymap here.
+    let action = app.keymap.action(key).filter(|&action| {
+        action == Action::Quit
+            || !matches!(key.code, KeyCode::Char(_))
+            || key.modifiers.contains(KeyModifiers::CONTROL)
+    });
+    match action {
+        Some(Action::Quit) => app.request_quit(),
+        Some(Action::MoveDown) if app.files.selected.is_some() => {
+            let len = app.visible_files().len();
+            app.files.selected = step_selection(app.files.selected, len, true, app.wrap_navigation);
+        }
+        Some(Action::MoveUp) if app.files.selected.is_some() => {
+            let len = app.visible_files().len();
+            app.files.selected =
+                step_selection(app.files.selected, len, false, app.wrap_navigation);
+        }
+        Some(Action::MoveTop) if app.files.selected.is_some() => {
+            app.files.selected = Some(0);
+        }
+        Some(Action::MoveBottom) if app.files.selected.is_some() => {
+            app.files.selected = Some(app.visible_files().len() - 1);
+        }
+        Some(Action::Open) => {
+            if app.select_typed_path() || app.selected_file().is_some() {
+                app.pending_load = true;
+                app.tabs.index = 1;
+            } else if !app.files.filter.is_empty() {
+                app.notify(format!("No match for '{}'", app.files.filter));
+            }
+        }
+        Some(Action::OpenAll) if !app.files.items.is_empty() => app.load_all_files(),
+        Some(Action::OpenRecent) => app.modal = Some(ModalState::new_input(ModalKind::Recent)),
+        Some(Action::NextTab) => app.next_tab(),
+        Some(Action::PrevTab) => app.prev_tab(),
+        Some(_) => {}
+        None => match key.code {
+            KeyCode::Char(c) => {
+                let mut filter = std::mem::take(&mut app.files.filter);
+                filter.push(c);
+                app.set_file_filter(filter);
+            }
+            KeyCode::Backspace => {
+                let mut filter = std::mem::take(&mut app.files.filter);
+                filter.pop();
+                app.set_file_filter(filter);
+            }
+            _ => {}
+        },
+    }
+}
+
+pub fn handle_vcf_tab(app: &mut App, key: crossterm::event::KeyEvent) {
+    let Some(action) = chord_action(app, key) else {
+        return;
+    };
+
+    if app.vcf.detail_focused {
+        match action {
+            Action::MoveDown => {
+                app.vcf.detail_scroll = (app.vcf.detail_scroll + 1).min(app.vcf.detail_max_scroll);
+            }
+            Action::MoveUp => {
+                app.vcf.detail_scroll = app.vcf.detail_scroll.saturating_sub(1);
+            }
+            Action::MoveTop => app.vcf.detail_scroll = 0,
+            Action::MoveBottom => app.vcf.detail_scroll = app.vcf.detail_max_scroll,
+            Action::FocusDetail | Action::Back => app.vcf.detail_focused = false,
+            _ => {}
+        }
+        return;
+    }
+    if app.vcf.group_by.is_some() && run_group_action(app, action) {
+        return;
+    }
+    run_vcf_action(app, action);
+}
+
+/// Moving and expanding in the grouped list; false for actions that work
+/// as in the table.
+fn run_group_action(app: &mut App, action: Action) -> bool {
+    let len = app.group_rows().len();
+    let selected = app.vcf.group_selected;
+    app.vcf.group_selected = match action {
+        Action::MoveDown => step_selection(selected, len, true, app.wrap_navigation),
+        Action::MoveUp => step_selection(selected, len, false, app.wrap_navigation),
+        Action::MoveTop => (len > 0).then_some(0),
+        Action::MoveBottom => len.checked_sub(1),
+        // Enter expands or collapses the group around the selection, even
+        // on one of its records; ShowRecord opens those as in the table.
+        Action::Open => {
+            app.toggle_group();
+            return true;
+        }
+        Action::Back => {
+            app.set_group_by(None);
+            return true;
+        }
+        _ => return false,
+    };
+    app.vcf.detail_scroll = 0;
+    true
+}
+
+/// Carries out `action` in the variant list; shared with the command
+/// palette, which can name any action.
+fn run_vcf_action(app: &mut App, action: Action) {
+    match action {
+        Action::Quit | Action::Back => {
+            app.tabs.index = 0;
+        }
+        Action::MoveDown => {
+            let len = app.classify_records().len();
+            app.vcf.selected = step_selection(app.vcf.selected, len, true, app.wrap_navigation);
+            app.vcf.detail_scroll = 0;
+        }
+        Action::MoveUp if app.vcf.selected.is_some() => {
+            let len = app.classify_records().len();
+            app.vcf.selected = step_selection(app.vcf.selected, len, false, app.wrap_navigation);
+            app.vcf.detail_scroll = 0;
+        }
+        Action::MoveTop | Action::MoveBottom => {
+            let len = app.classify_records().len();
+            if len > 0 {
+                app.vcf.selected = Some(if action == Action::MoveTop {
+                    0
+                } else {
+                    len - 1
+                });
+            }
+            app.vcf.detail_scroll = 0;
+        }
+        Action::OpenFilter => {
+            app.modal = Some(ModalState::new_menu());
+        }
+        Action::ToggleDimmed => app.toggle_dimmed(),
+        Action::ToggleCase => {
+            app.vcf.filters.case_sensitive = !app.vcf.filters.case_sensitive;
+            app.clamp_selection();
+        }
+        Action::ToggleZeroBased => app.vcf.zero_based = !app.vcf.zero_based,
+        Action::ToggleRelativePos => app.vcf.relative_pos = !app.vcf.relative_pos,
+        Action::ToggleLegend => app.vcf.show_legend = !app.vcf.show_legend,
+        Action::ToggleWrap => app.vcf.wrap_selected = !app.vcf.wrap_selected,
+        Action::ToggleTypeColors => app.vcf.plain_rows = !app.vcf.plain_rows,
+        Action::OpenRecent => app.modal = Some(ModalState::new_input(ModalKind::Recent)),
+        Action::Reload => app.reload(),
+        Action::Open | Action::ShowRecord if app.selected_record().is_some() => {
+            app.modal = Some(ModalState::new_input(ModalKind::Record));
+        }
+        Action::GroupBy => app.modal = Some(ModalState::new_input(ModalKind::GroupBy)),
+        Action::OpenColumns => app.modal = Some(ModalState::new_input(ModalKind::Columns)),
+        Action::FocusDetail if app.vcf.selected.is_some() => {
+            app.vcf.detail_focused = true;
+            app.vcf.detail_scroll = 0;
+        }
+        Action::ToggleBed if app.vcf.filters.bed.is_some() => {
+            app.vcf.filters.bed_mode = app.vcf.filters.bed_mode.next();
+            app.clamp_selection();
+        }
+        Action::WriteReport => app.export_report(),
+        Action::CopyFiltered => app.copy_filtered(),
+        Action::ResetFilters => {
+            app.reset_filters();
+            app.notify("Filters cleared for this file".to_string());
+        }
+        Action::SortRecords => app.sort_records(),
+        Action::ToggleBookmark => app.toggle_bookmark(),
+        Action::OpenExport => app.modal = Some(ModalState::new_input(ModalKind::Export)),
+        Action::NextChrom => app.jump_chrom(true),
+        Action::PrevChrom => app.jump_chrom(false),
+        Action::ScrollLeft => app.vcf.scroll_columns(false),
+        Action::ScrollRight => app.vcf.scroll_columns(true),
+        Action::NarrowFilters => app.resize_filter_pane(false),
+        Action::WidenFilters => app.resize_filter_pane(true),
+        Action::ToggleHead => app.set_head(None),
+        Action::ToggleAlleles => app.vcf.gt_alleles = !app.vcf.gt_alleles,
+        Action::OpenEditor => app.open_in_editor(),
+        Action::CommandPalette => app.modal = Some(ModalState::new_input(ModalKind::Command)),
+        Action::SortNext => app.cycle_sort_column(),
+        Action::SortReverse => {
+            if let Some(column) = app.vcf.sort_column {
+                app.sort_by_column(column);
+            }
+        }
+        Action::NextTab => app.next_tab(),
+        Action::PrevTab => app.prev_tab(),
+        _ => {}
+    }
+}
+
+pub fn handle_stats_tab(app: &mut App, key: crossterm::event::KeyEvent) {
+    match chord_action(app, key) {
+        Some(Action::Quit | Action::Back) => app.tabs.index = 0,
+        Some(Action::NextChrom) => app.jump_chrom(true),
+        Some(Action::PrevChrom) => app.jump_chrom(false),
+        Some(Action::NextTab) => app.next_tab(),
+        Some(Action::PrevTab) => app.prev_tab(),
+        Some(Action::WriteReport) => app.export_report(),
+        Some(Action::CommandPalette) => app.modal = Some(ModalState::new_input(ModalKind::Command)),
+        _ => {}
+    }
+}
+
+fn set_region(app: &mut App, region: &str) {
+    if !app.vcf.filters.set_region(region) {
+        app.notify(format!(
+            "Invalid region '{}', expected e.g. chr1:1,000-5,000",
+            region
+        ));
+    }
+}
+
+/// Runs a line typed into the command palette, reporting parse errors as
+/// a toast.
+fn run_command(app: &mut App, line: &str) {
+    let command = match parse_command(line) {
+        Ok(command) => command,
+        Err(e) => {
+            app.notify(e);
+            return;
+        }
+    };
+    match command {
+        Command::Sort(column, descending) => app.set_sort(column, descending),
+        Command::Filter(ModalKind::Region, value) => set_region(app, &value),
+        Command::Filter(kind, value) => {
+            if let Some(field) = app.filter_field(kind) {
+                *field = value;
+            }
+        }
+        Command::Export(Some(path)) if path.extension().is_some_and(|ext| ext == "bed") => {
+            app.export_bed(&path)
+        }
+        Command::Export(Some(path)) => app.export_to(ExportItem::Filtered, &path),
+        Command::Export(None) => app.export(ExportItem::Filtered),
+        Command::Bed(path) => app.load_bed(&path),
+        Command::Tab(index) => app.tabs.index = index,
+        Command::Group(key) => app.set_group_by(key),
+        Command::Head(limit) => app.set_head(limit),
+        Command::Action(Action::Quit) => app.request_quit(),
+        Command::Action(action) => run_vcf_action(app, action),
+    }
+}
+
+pub fn handle_modal_key(app: &mut App, key: crossterm::event::KeyEvent) {
+    let count_delay = if app.vcf.records.len() > LIVE_COUNT_LIMIT {
+        LIVE_COUNT_DELAY
+    } else {
+        Duration::ZERO
+    };
+    let modal = app.modal.as_mut().unwrap();
+
+    match modal.kind {
+        ModalKind::Menu => match key.code {
+            KeyCode::Up if modal.menu_selected > 0 => {
+                modal.menu_selected -= 1;
+            }
+            KeyCode::Down if modal.menu_selected + 1 < MENU_ITEMS.len() => {
+                modal.menu_selected += 1;
+            }
+            KeyCode::Enter => {
+                match MENU_ITEMS[modal.menu_selected] {
+                    MenuItem::Chrom => app.modal = Some(ModalState::new_input(ModalKind::Chrom)),
+                    MenuItem::Ref => app.modal = Some(ModalState::new_input(ModalKind::Ref)),
+                    MenuItem::Alt => app.modal = Some(ModalState::new_input(ModalKind::Alt)),
+                    MenuItem::Pos => app.modal = Some(ModalState::new_input(ModalKind::Pos)),
+                    MenuItem::Region => app.modal = Some(ModalState::new_input(ModalKind::Region)),
+                    MenuItem::Id => app.modal = Some(ModalState::new_input(ModalKind::Id)),
+                    MenuItem::NovelOnly => app.toggle_id_presence(IdPresence::Novel),
+                    MenuItem::KnownOnly => app.toggle_id_presence(IdPresence::Known),
+                    MenuItem::IndelLen => {
+                        app.modal = Some(ModalState::new_input(ModalKind::IndelLen))
+                    }
+                    MenuItem::SvType => app.modal = Some(ModalState::new_input(ModalKind::SvType)),
+                    MenuItem::Info => app.modal = Some(ModalState::new_input(ModalKind::Info)),
+                    MenuItem::Flag => app.modal = Some(ModalState::new_input(ModalKind::Flag)),
+                    MenuItem::NonMissing => {
+                        app.modal = Some(ModalState::new_input(ModalKind::Columns))
+                    }
+                    MenuItem::MultiAllelic => {
+                        app.vcf.filters.multiallelic_only = !app.vcf.filters.multiallelic_only
+                    }
+                    MenuItem::Bed => app.modal = Some(ModalState::new_input(ModalKind::Bed)),
+                    MenuItem::ClearAll => {
+                        app.reset_filters();
+                        app.modal = None;
+                    }
+                    MenuItem::Cancel => app.modal = None,
+                }
+                app.clamp_selection();
+            }
+            KeyCode::Delete | KeyCode::Backspace => {
+                let item = MENU_ITEMS[modal.menu_selected];
+                app.clear_filter(item);
+                app.clamp_selection();
+            }
+            KeyCode::Esc => app.modal = None,
+            _ => {}
+        },
+        ModalKind::Columns => match key.code {
+            KeyCode::Up if modal.menu_selected > 0 => {
+                modal.menu_selected -= 1;
+            }
+            KeyCode::Down if modal.menu_selected + 1 < ALL_COLUMNS.len() => {
+                modal.menu_selected += 1;
+            }
+            KeyCode::Enter | KeyCode::Char(' ') => {
+                let column = ALL_COLUMNS[modal.menu_selected];
+                app.toggle_column(column);
+            }
+            KeyCode::Char('n') => {
+                let column = ALL_COLUMNS[modal.menu_selected];
+                app.toggle_non_missing(column);
+            }
+            KeyCode::Esc | KeyCode::Char('v') => app.modal = None,
+            _ => {}
+        },
+        ModalKind::Recent => match key.code {
+            KeyCode::Up if modal.menu_selected > 0 => {
+                modal.menu_selected -= 1;
+            }
+            KeyCode::Down if modal.menu_selected + 1 < app.recent.len() => {
+                modal.menu_selected += 1;
+            }
+            KeyCode::Enter => {
+                let index = modal.menu_selected;
+                app.modal = None;
+                app.open_recent(index);
+            }
+            KeyCode::Esc => app.modal = None,
+            _ => {}
+        },
+        ModalKind::Record => match key.code {
+            KeyCode::Down | KeyCode::Char('j') => {
+                modal.scroll = (modal.scroll + 1).min(modal.max_scroll);
+            }
+            KeyCode::Up | KeyCode::Char('k') => modal.scroll = modal.scroll.saturating_sub(1),
+            KeyCode::PageDown | KeyCode::Char(' ') => {
+                modal.scroll = (modal.scroll + 10).min(modal.max_scroll);
+            }
+            KeyCode::PageUp => modal.scroll = modal.scroll.saturating_sub(10),
+            KeyCode::Home => modal.scroll = 0,
+            KeyCode::End => modal.scroll = modal.max_scroll,
+            KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q') => app.modal = None,
+            _ => {}
+        },
+        ModalKind::ConfirmQuit => match key.code {
+            KeyCode::Char('y' | 'Y') => app.quit(),
+            KeyCode::Char('n' | 'N') | KeyCode::Esc => app.modal = None,
+            _ => {}
+        },
+        ModalKind::Export => match key.code {
+            KeyCode::Up if modal.menu_selected > 0 => {
+                modal.menu_selected -= 1;
+            }
+            KeyCode::Down if modal.menu_selected + 1 < EXPORT_ITEMS.len() => {
+                modal.menu_selected += 1;
+            }
+            KeyCode::Enter => {
+                let item = EXPORT_ITEMS[modal.menu_selected];
+                app.modal = None;
+                app.export(item);
+            }
+            KeyCode::Esc => app.modal = None,
+            _ => {}
+        },
+        _ => match key.code {
+            KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                modal.set_input(String::new());
+                modal.count_due = Some(Instant::now() + count_delay);
+            }
+            KeyCode::Char('w') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                modal.delete_word();
+                modal.count_due = Some(Instant::now() + count_delay);
+            }
+            // Other Ctrl chords aren't text.
+            KeyCode::Char(_) if key.modifiers.contains(KeyModifiers::CONTROL) => {}
+            KeyCode::Char(c) => {
+                modal.insert(c);
+                modal.count_due = Some(Instant::now() + count_delay);
+            }
+            KeyCode::Backspace | KeyCode::Delete => {
+                modal.delete(key.code == KeyCode::Delete);
+                modal.count_due = Some(Instant::now() + count_delay);
+            }
+            KeyCode::Left => modal.move_cursor(modal.cursor.saturating_sub(1)),
+            KeyCode::Right => modal.move_cursor(modal.cursor + 1),
+            KeyCode::Home => modal.move_cursor(0),
+            KeyCode::End => modal.move_cursor(usize::MAX),
+            KeyCode::Tab if modal.kind == ModalKind::Command => {
+                if let Some(completed) = complete_command(&modal.input) {
+                    modal.set_input(completed);
+                }
+            }
+            KeyCode::Tab
+                if matches!(
+                    modal.kind,
+                    ModalKind::Info | ModalKind::Flag | ModalKind::GroupBy
+                ) =>
+            {
+                if let Some(completed) = complete_info_key(&app.vcf.info_keys, &modal.input) {
+                    modal.set_input(completed);
+                    modal.count_due = Some(Instant::now() + count_delay);
+                }
+            }
+            KeyCode::Up | KeyCode::Down => {
+                let history = app.history.get(&modal.kind).map_or(&[][..], Vec::as_slice);
+                let pos = match (key.code, modal.history_pos) {
+                    (KeyCode::Up, None) => history.len().checked_sub(1),
+                    (KeyCode::Up, Some(p)) => Some(p.saturating_sub(1)),
+                    (_, Some(p)) if p + 1 < history.len() => Some(p + 1),
+                    _ => None,
+                };
+                if pos.is_some() || modal.history_pos.is_some() {
+                    modal.set_input(pos.map(|p| history[p].clone()).unwrap_or_default());
+                }
+                modal.history_pos = pos;
+                modal.count_due = Some(Instant::now() + count_delay);
+            }
+            KeyCode::Enter => {
+                let txt = modal.input.trim().to_string();
+                let history = app.history.entry(modal.kind).or_default();
+                if !txt.is_empty() && history.last() != Some(&txt) {
+                    history.push(txt.clone());
+                }
+                let kind = modal.kind;
+                // Closed first: a palette command may open another modal.
+                app.modal = None;
+                if kind == ModalKind::Bed {
+                    app.load_bed(Path::new(&txt));
+                } else if kind == ModalKind::Command {
+                    run_command(app, &txt);
+                } else if kind == ModalKind::Region {
+                    set_region(app, &txt);
+                } else if kind == ModalKind::GroupBy {
+                    app.set_group_by(Some(txt));
+                } else if let Some(field) = app.filter_field(kind) {
+                    *field = txt;
+                }
+                app.clamp_selection();
+            }
+            KeyCode::Esc => app.modal = None,
+            _ => {}
+        },
+    }
+
+    if app
+        .modal
+        .as_ref()
+        .and_then(|m| m.count_due)
+        .is_some_and(|due| due <= Instant::now())
+    {
+        app.refresh_match_count();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(app: &mut App, c: char) -> Option<Action> {
+        chord_action(app, KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE))
+    }
+
+    #[test]
+    fn chords_wait_for_their_second_key() {
+        let mut app = App::default();
+        assert_eq!(press(&mut app, 'g'), None);
+        assert_eq!(press(&mut app, 'g'), Some(Action::MoveTop));
+        assert_eq!(press(&mut app, 'G'), Some(Action::MoveBottom));
+
+        // A key that doesn't finish the chord counts on its own.
+        assert_eq!(press(&mut app, 'd'), None);
+        assert_eq!(press(&mut app, 'j'), Some(Action::MoveDown));
+        assert!(app.pending_key.is_none());
+    }
+
+    #[test]
+    fn palette_commands_are_dispatched() {
+        let mut app = App::default();
+        handle_vcf_tab(
+            &mut app,
+            KeyEvent::new(KeyCode::Char(':'), KeyModifiers::NONE),
+        );
+        let modal = app.modal.as_mut().unwrap();
+        assert_eq!(modal.kind, ModalKind::Command);
+        modal.input = "sort qual desc".to_string();
+        handle_modal_key(&mut app, KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        assert!(app.modal.is_none());
+        assert_eq!(app.vcf.sort_column, Some(crate::app::Column::Qual));
+        assert!(app.vcf.sort_descending);
+
+        run_command(&mut app, "filter chrom 20");
+        assert_eq!(app.vcf.filters.chrom, "20");
+        run_command(&mut app, "open_filter");
+        assert_eq!(app.modal.as_ref().map(|m| m.kind), Some(ModalKind::Menu));
+    }
+
+    #[test]
+    fn grouped_enter_toggles_and_space_shows_the_record() {
+        let data = "1\t100\t.\tA\t<DEL>\t.\t.\tSVTYPE=DEL\n";
+        let mut app = App::default();
+        app.vcf.records = crate::vcf::parse_vcf_reader(std::io::Cursor::new(data))
+            .unwrap()
+            .records
+            .into();
+        app.set_group_by(Some("SVTYPE".to_string()));
+        let enter = KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE);
+        handle_vcf_tab(&mut app, enter);
+        app.vcf.group_selected = Some(1);
+        handle_vcf_tab(
+            &mut app,
+            KeyEvent::new(KeyCode::Char(' '), KeyModifiers::NONE),
+        );
+        assert_eq!(app.modal.take().map(|m| m.kind), Some(ModalKind::Record));
+        handle_vcf_tab(&mut app, enter);
+        assert!(app.modal.is_none());
+        assert_eq!(app.group_rows().len(), 1);
+    }
+}
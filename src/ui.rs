@@ -0,0 +1,1688 @@
+//! Rendering for every tab, panel and modal.
+
+use ratatui::{
+    Terminal,
+    backend::Backend,
+    buffer::Buffer,
+    layout::{Constraint, Direction, Flex, Layout, Position, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span, Text},
+    widgets::{
+        Block, Borders, Cell, Clear, Gauge, List, ListItem, ListState, Paragraph, Row, Sparkline,
+        Table, Tabs, Wrap,
+    },
+};
+use std::{borrow::Cow, fs, path::Path, sync::atomic::Ordering};
+
+use crate::{
+    app::{
+        ALL_COLUMNS, App, Column, EXPORT_ITEMS, ExportItem, ExportJob, GroupRow, MENU_ITEMS,
+        ModalKind, ModalState, RecordChange,
+    },
+    command::command_suggestions,
+    filter::{IdPresence, info_suggestions},
+    input::Action,
+    vcf::{AlleleClass, GtCounts, VariantStats, VcfRecord, percent_decode},
+};
+
+/// Below this width the VCF tab stacks everything in one column.
+const NARROW_WIDTH: u16 = 100;
+
+pub fn ui(f: &mut ratatui::Frame, app: &mut App) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Length(3),
+                Constraint::Min(0),
+                Constraint::Length(1),
+            ]
+            .as_ref(),
+        )
+        .split(f.area());
+
+    let titles: Vec<_> = app.tabs.titles.iter().cloned().map(Line::from).collect();
+    let tabs = Tabs::new(titles)
+        .block(Block::default().borders(Borders::ALL).title("VCF TUI"))
+        .select(app.tabs.index)
+        .style(Style::default().fg(Color::Cyan))
+        .highlight_style(
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        );
+    f.render_widget(tabs, chunks[0]);
+
+    match app.tabs.index {
+        0 => render_file_tab(f, app, chunks[1]),
+        1 => render_vcf_tab(f, app, chunks[1]),
+        2 => render_stats_tab(f, app, chunks[1]),
+        _ => {}
+    }
+    render_status_bar(f, app, chunks[2]);
+
+    if let Some(modal) = &app.modal {
+        let max_scroll = render_modal(f, modal, app);
+        if let Some(modal) = &mut app.modal {
+            modal.max_scroll = max_scroll;
+        }
+    }
+    if app.no_color {
+        strip_colors(f.buffer_mut());
+    }
+}
+
+/// Drops every colour from a drawn frame for `NO_COLOR` or dumb terminals.
+/// Backgrounds (selection, status bar) become reverse video, warning
+/// colours bold and greyed-out text dim, so emphasis survives.
+fn strip_colors(buf: &mut Buffer) {
+    for cell in &mut buf.content {
+        if cell.bg != Color::Reset {
+            cell.modifier |= Modifier::REVERSED;
+        }
+        match cell.fg {
+            Color::Red | Color::LightRed | Color::Yellow => cell.modifier |= Modifier::BOLD,
+            Color::DarkGray => cell.modifier |= Modifier::DIM,
+            _ => {}
+        }
+        cell.fg = Color::Reset;
+        cell.bg = Color::Reset;
+    }
+}
+
+fn render_file_tab(f: &mut ratatui::Frame, app: &mut App, area: ratatui::layout::Rect) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Min(0)].as_ref())
+        .split(area);
+
+    let filter = Paragraph::new(format!("Filter: {}", app.files.filter))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("File Filter (fuzzy)"),
+        )
+        .style(Style::default().fg(Color::Yellow));
+    f.render_widget(filter, chunks[0]);
+
+    let scanning = if app.files.scan.is_some() {
+        format!(" scanning... {} found", app.files.items.len())
+    } else {
+        String::new()
+    };
+    let block = Block::default().borders(Borders::ALL).title(format!(
+        "VCF Files (Up/Down move, {} open, {} open all combined, {} recent){}",
+        app.keymap.hint(Action::Open),
+        app.keymap.hint(Action::OpenAll),
+        app.keymap.hint(Action::OpenRecent),
+        scanning
+    ));
+    let visible = app.visible_files();
+    if visible.is_empty() {
+        let message = if app.files.scan.is_some() {
+            "Scanning for VCF files...".to_string()
+        } else if app.files.items.is_empty() {
+            let root = fs::canonicalize(&app.files.root).unwrap_or_else(|_| app.files.root.clone());
+            format!("No VCF files found in {}", root.display())
+        } else {
+            "No files match the filter".to_string()
+        };
+        let placeholder = Paragraph::new(message)
+            .block(block)
+            .style(Style::default().fg(Color::DarkGray));
+        f.render_widget(placeholder, chunks[1]);
+        return;
+    }
+
+    let (list_area, preview_area) = if chunks[1].width >= NARROW_WIDTH {
+        let halves = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(65), Constraint::Percentage(35)])
+            .split(chunks[1]);
+        (halves[0], Some(halves[1]))
+    } else {
+        (chunks[1], None)
+    };
+    let items: Vec<ListItem> = visible
+        .iter()
+        .enumerate()
+        .map(|(i, path)| {
+            let name = path
+                .file_name()
+                .unwrap_or(path.as_os_str())
+                .to_string_lossy()
+                .into_owned();
+            let style = if Some(i) == app.files.selected {
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default()
+            };
+            // Match positions are in the full path; the name is its tail.
+            let matched = app.file_match(path).map(|(_, m)| m).unwrap_or_default();
+            let skip = path.to_string_lossy().chars().count() - name.chars().count();
+            // `●` marks the file open in the viewer, which needn't be the
+            // highlighted row.
+            let marker = if app.files.loaded.as_ref() == Some(*path) {
+                Span::styled("● ", Style::default().fg(Color::Green))
+            } else {
+                Span::raw("  ")
+            };
+            let spans: Vec<Span> = std::iter::once(marker)
+                .chain(name.chars().enumerate().map(|(n, c)| {
+                    if matched.contains(&(skip + n)) {
+                        Span::styled(c.to_string(), style.add_modifier(Modifier::UNDERLINED))
+                    } else {
+                        Span::styled(c.to_string(), style)
+                    }
+                }))
+                .collect();
+            ListItem::new(Line::from(spans))
+        })
+        .collect();
+
+    let list = List::new(items)
+        .block(block)
+        .highlight_style(Style::default().bg(Color::DarkGray));
+    app.files.list_state.select(app.files.selected);
+    f.render_stateful_widget(list, list_area, &mut app.files.list_state);
+    if let Some(area) = preview_area {
+        render_file_preview(f, app, area);
+    }
+}
+
+/// Records per chromosome of the highlighted file, from the quick CHROM
+/// scan `App::poll_preview` runs.
+fn render_file_preview(f: &mut ratatui::Frame, app: &App, area: Rect) {
+    let block = Block::default().borders(Borders::ALL).title("Chromosomes");
+    let dim = Style::default().fg(Color::DarkGray);
+    let lines = match app.selected_file().map(|p| app.files.previews.get(p)) {
+        None => Vec::new(),
+        Some(None) => vec![Line::styled("Scanning...", dim)],
+        Some(Some(Err(e))) => vec![Line::styled(e.clone(), dim)],
+        Some(Some(Ok(counts))) if counts.is_empty() => vec![Line::styled("No records", dim)],
+        Some(Some(Ok(counts))) => {
+            let total: usize = counts.iter().map(|(_, n)| n).sum();
+            let width = counts.iter().map(|(c, _)| c.chars().count()).max();
+            let mut lines = vec![Line::styled(
+                format!("{} records on {} chromosomes", total, counts.len()),
+                Style::default().fg(Color::Cyan),
+            )];
+            let digits = total.to_string().len();
+            lines.extend(counts.iter().map(|(chrom, n)| {
+                Line::from(format!(
+                    "{:<w$}  {:>d$}",
+                    chrom,
+                    n,
+                    w = width.unwrap_or(0),
+                    d = digits
+                ))
+            }));
+            lines
+        }
+    };
+    let preview = Paragraph::new(lines)
+        .block(block)
+        .wrap(Wrap { trim: false });
+    f.render_widget(preview, area);
+}
+
+fn render_vcf_tab(f: &mut ratatui::Frame, app: &mut App, area: ratatui::layout::Rect) {
+    let truncated = app.head_truncation();
+    let outer = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(1),
+            Constraint::Length(u16::from(truncated.is_some())),
+            Constraint::Min(0),
+        ])
+        .split(area);
+    render_filter_chips(f, app, outer[0]);
+    if let Some((shown, matching)) = truncated {
+        render_truncation_banner(f, app, shown, matching, outer[1]);
+    }
+
+    // Narrow terminals (tmux side panes) drop the filter column; the chips
+    // line above still shows what is active.
+    let (table_area, context_area) = if outer[2].width < NARROW_WIDTH {
+        (outer[2], None)
+    } else {
+        let chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Percentage(app.vcf.filter_pane),
+                Constraint::Percentage(100 - app.vcf.filter_pane),
+            ])
+            .split(outer[2]);
+        (chunks[1], Some(render_filter_boxes(f, app, chunks[0])))
+    };
+
+    let right = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(10)])
+        .split(table_area);
+
+    if app.vcf.group_by.is_some() {
+        render_group_list(f, app, right[0]);
+    } else {
+        render_variant_table(f, app, right[0]);
+    }
+
+    let selected = app.selected_record();
+    if let Some(context_area) = context_area
+        && app.context_rows > 0
+    {
+        render_context_panel(f, app, selected, context_area);
+    }
+    let max_scroll = render_detail_panel(f, app, selected, right[1]);
+    app.vcf.detail_max_scroll = max_scroll;
+}
+
+fn render_variant_table(f: &mut ratatui::Frame, app: &mut App, area: Rect) {
+    // Mirror the table's own column layout so header clicks can be mapped
+    // back to a column.
+    let header_row = Block::default().borders(Borders::ALL).inner(area);
+    let header_row = Rect {
+        height: header_row.height.min(1),
+        ..header_row
+    };
+    let columns = app.vcf.scrolled_columns();
+    let cells = Layout::horizontal(columns.iter().map(|c| app.vcf.column_width(*c)))
+        .flex(Flex::Start)
+        .spacing(1)
+        .split(header_row);
+    app.vcf.header_cells = columns.iter().copied().zip(cells.iter().copied()).collect();
+
+    // Taken out for the frame and put back at the end, since `rows` borrows
+    // `app`.
+    let mut table_state = std::mem::take(&mut app.vcf.table_state);
+    table_state.select(app.vcf.selected);
+    let rows = app.display_rows();
+
+    let header = Row::new(columns.iter().map(|&c| {
+        let glyph = match app.vcf.sort_column {
+            Some(s) if s == c && app.vcf.sort_descending => " ▼",
+            Some(s) if s == c => " ▲",
+            _ => "",
+        };
+        Cell::from(format!("{}{}", c.label(), glyph))
+    }))
+    .style(
+        Style::default()
+            .fg(Color::Cyan)
+            .add_modifier(Modifier::BOLD),
+    );
+    // With relative POS, offsets are taken from the row the table will
+    // scroll to the top, mirroring how it keeps the selection in view.
+    let anchor = if app.vcf.relative_pos {
+        let height = area.height.saturating_sub(3) as usize;
+        let mut top = table_state.offset().min(rows.len().saturating_sub(1));
+        if let Some(selected) = app.vcf.selected {
+            if selected < top {
+                top = selected;
+            } else if height > 0 && selected >= top + height {
+                top = selected + 1 - height;
+            }
+        }
+        rows.get(top).map(|(r, _)| (r.chrom.as_str(), r.pos))
+    } else {
+        None
+    };
+    let body: Vec<Row> = rows
+        .iter()
+        .enumerate()
+        .map(|(i, (r, matched))| {
+            let style = if Some(i) == app.vcf.selected {
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD)
+            } else if let Some(change) = app.record_change(r) {
+                let bg = match change {
+                    RecordChange::Added => Color::Green,
+                    RecordChange::Changed => Color::Yellow,
+                };
+                Style::default().fg(Color::Black).bg(bg)
+            } else if !matched {
+                Style::default().fg(Color::DarkGray)
+            } else if app.is_bookmarked(r) {
+                Style::default().fg(Color::Magenta)
+            } else if app.vcf.plain_rows {
+                Style::default()
+            } else {
+                Style::default().fg(class_color(r.allele_class()))
+            };
+            let wrapped = app.vcf.wrap_selected && Some(i) == app.vcf.selected;
+            let mut height = 1;
+            let cells: Vec<Cell> = columns
+                .iter()
+                .enumerate()
+                .map(|(n, &c)| {
+                    let value = match anchor {
+                        Some((chrom, at)) if c == Column::Pos && r.chrom == chrom => {
+                            relative_pos(r.pos, at)
+                        }
+                        _ => c.value(r, &app.vcf),
+                    };
+                    let spans = match app.filter_highlight(c, &value) {
+                        Some(m) => vec![
+                            Span::raw(value[..m.start].to_string()),
+                            Span::styled(
+                                value[m.clone()].to_string(),
+                                Style::default().add_modifier(Modifier::UNDERLINED),
+                            ),
+                            Span::raw(value[m.end..].to_string()),
+                        ],
+                        None if c == Column::Filter && *matched => {
+                            let color = filter_color(&value);
+                            vec![Span::styled(value.clone(), Style::default().fg(color))]
+                        }
+                        None => vec![Span::raw(value.clone())],
+                    };
+                    let width = cells.get(n).map_or(0, |cell| cell.width as usize);
+                    let mut lines = if wrapped && matches!(c, Column::Ref | Column::Alt) {
+                        wrap_chars(&value, width)
+                    } else {
+                        vec![ellipsize(Line::from(spans), width)]
+                    };
+                    height = height.max(lines.len() as u16);
+                    // Flagged by validation: mark the row in its first cell.
+                    if n == 0 && r.problem.is_some() {
+                        lines[0]
+                            .spans
+                            .insert(0, Span::styled("⚠ ", Style::default().fg(Color::Red)));
+                    }
+                    Cell::from(Text::from(lines))
+                })
+                .collect();
+            Row::new(cells).height(height).style(style)
+        })
+        .collect();
+
+    let scrolled = app.vcf.table_columns().len() - columns.len();
+    let matched = rows.iter().filter(|(_, matched)| *matched).count();
+    let total = app.vcf.records.len();
+    let counts = format!(
+        " {} matched / {} total ({:.1}%) ",
+        matched,
+        total,
+        matched as f64 * 100.0 / total.max(1) as f64
+    );
+    let hints = format!(
+        " ({} = filter menu, {} = columns, {} = sort, {} = commands)",
+        app.keymap.hint(Action::OpenFilter),
+        app.keymap.hint(Action::OpenColumns),
+        app.keymap.hint(Action::SortNext),
+        app.keymap.hint(Action::CommandPalette)
+    );
+    let mut suffix = format!(
+        " [POS {}]{}{}",
+        if app.vcf.relative_pos {
+            "relative"
+        } else {
+            app.vcf.coordinate_label()
+        },
+        app.head_cap()
+            .map(|n| format!(" [first {}]", n))
+            .unwrap_or_default(),
+        if scrolled > 0 {
+            format!(
+                " [{} columns ◀ {}]",
+                scrolled,
+                app.keymap.hint(Action::ScrollLeft)
+            )
+        } else {
+            String::new()
+        },
+    );
+    // The key hints give way first so the counts and ~20 characters of the
+    // path stay readable.
+    let needed = suffix.chars().count() + hints.chars().count() + counts.len() + 24;
+    if area.width as usize >= needed {
+        suffix.push_str(&hints);
+    }
+    let title = match &app.vcf.path {
+        Some(path) => {
+            let prefix = if app.vcf.combined {
+                "All files in "
+            } else {
+                ""
+            };
+            let room = (area.width as usize)
+                .saturating_sub(prefix.len() + suffix.chars().count() + counts.len() + 4);
+            format!(
+                "{}{}{}",
+                prefix,
+                truncate_middle(&path.display().to_string(), room),
+                suffix
+            )
+        }
+        None => format!("Variants{}", suffix),
+    };
+    let table = Table::new(body, columns.iter().map(|c| app.vcf.column_width(*c)))
+        .header(header)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(title)
+                .title(Line::from(counts).right_aligned()),
+        )
+        .highlight_style(Style::default().bg(Color::DarkGray));
+
+    f.render_stateful_widget(table, area, &mut table_state);
+    if app.vcf.show_legend {
+        render_legend(f, app, area);
+    }
+    app.vcf.table_state = table_state;
+}
+
+/// The variant list grouped by an INFO value: a header per group with its
+/// record count, and the records of expanded groups below it.
+fn render_group_list(f: &mut ratatui::Frame, app: &mut App, area: Rect) {
+    // No table header to click while grouped.
+    app.vcf.header_cells.clear();
+    let mut list_state = std::mem::take(&mut app.vcf.group_state);
+    list_state.select(app.vcf.group_selected);
+    let groups = app.info_groups();
+    let matched: usize = groups.iter().map(|(_, records)| records.len()).sum();
+    let items: Vec<ListItem> = app
+        .group_rows()
+        .into_iter()
+        .map(|row| match row {
+            GroupRow::Group {
+                value,
+                count,
+                expanded,
+            } => ListItem::new(Line::styled(
+                format!("{} {} ({})", if expanded { "▾" } else { "▸" }, value, count),
+                Style::default()
+                    .fg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD),
+            )),
+            GroupRow::Record(r) => {
+                let pos = app.vcf.display_pos(r);
+                let label = r
+                    .sv_label(&pos)
+                    .unwrap_or_else(|| format!("{}:{} {}>{}", r.chrom, pos, r.ref_, r.alt));
+                let qual = r.qual_text();
+                let style = if app.is_bookmarked(r) {
+                    Style::default().fg(Color::Magenta)
+                } else if app.vcf.plain_rows {
+                    Style::default()
+                } else {
+                    Style::default().fg(class_color(r.allele_class()))
+                };
+                ListItem::new(Line::styled(
+                    format!("    {}  {}  {}", label, qual, r.filter),
+                    style,
+                ))
+            }
+        })
+        .collect();
+
+    let title = format!(
+        "Grouped by {}: {} groups{} (Enter expands, {} = ungroup, {} = group by)",
+        app.vcf.group_by.as_deref().unwrap_or_default(),
+        groups.len(),
+        app.head_cap()
+            .map(|n| format!(" in the first {}", n))
+            .unwrap_or_default(),
+        app.keymap.hint(Action::Back),
+        app.keymap.hint(Action::GroupBy)
+    );
+    let counts = format!(" {} matched / {} total ", matched, app.vcf.records.len());
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(title)
+                .title(Line::from(counts).right_aligned()),
+        )
+        .highlight_style(
+            Style::default()
+                .bg(Color::DarkGray)
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        );
+    f.render_stateful_widget(list, area, &mut list_state);
+    app.vcf.group_state = list_state;
+}
+
+/// Overlays a key to the colours and marks the variant list is using in
+/// the bottom-right corner of `area`.
+fn render_legend(f: &mut ratatui::Frame, app: &App, area: Rect) {
+    let mut entries = vec![
+        (
+            Span::styled(
+                "row",
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            "selected record",
+        ),
+        (
+            Span::styled("PASS", Style::default().fg(filter_color("PASS"))),
+            "FILTER passed",
+        ),
+        (
+            Span::styled(".", Style::default().fg(filter_color("."))),
+            "FILTER not set",
+        ),
+        (
+            Span::styled("q10", Style::default().fg(filter_color("q10"))),
+            "FILTER failed",
+        ),
+    ];
+    if !app.vcf.plain_rows {
+        let classes = [
+            (AlleleClass::Snp, "SNP"),
+            (AlleleClass::Insertion, "insertion"),
+            (AlleleClass::Deletion, "deletion"),
+            (AlleleClass::Complex, "MNP or mixed"),
+        ];
+        entries.extend(classes.map(|(class, meaning)| {
+            (
+                Span::styled("row", Style::default().fg(class_color(class))),
+                meaning,
+            )
+        }));
+    }
+    if app.vcf.changes.is_some() {
+        entries.extend([
+            (
+                Span::styled("row", Style::default().fg(Color::Black).bg(Color::Green)),
+                "new since reload",
+            ),
+            (
+                Span::styled("row", Style::default().fg(Color::Black).bg(Color::Yellow)),
+                "changed since reload",
+            ),
+        ]);
+    }
+    if app.vcf.show_dimmed {
+        entries.push((
+            Span::styled("row", Style::default().fg(Color::DarkGray)),
+            "fails the filters",
+        ));
+    }
+    if !app.vcf.bookmarks.is_empty() {
+        entries.push((
+            Span::styled("row", Style::default().fg(Color::Magenta)),
+            "bookmarked",
+        ));
+    }
+    let filters = &app.vcf.filters;
+    if [&filters.chrom, &filters.ref_, &filters.alt, &filters.id]
+        .iter()
+        .any(|v| !v.is_empty())
+    {
+        entries.push((
+            Span::styled("text", Style::default().add_modifier(Modifier::UNDERLINED)),
+            "matches a filter",
+        ));
+    }
+    if app.vcf.records.iter().any(|r| r.problem.is_some()) {
+        entries.push((
+            Span::styled("⚠", Style::default().fg(Color::Red)),
+            "malformed record",
+        ));
+    }
+
+    let lines: Vec<Line> = entries
+        .into_iter()
+        .map(|(swatch, meaning)| {
+            let pad = " ".repeat(5 - swatch.width().min(5));
+            Line::from(vec![swatch, Span::raw(format!("{} {}", pad, meaning))])
+        })
+        .collect();
+    let width = (lines.iter().map(Line::width).max().unwrap_or(0) as u16 + 2)
+        .min(area.width.saturating_sub(2));
+    let height = (lines.len() as u16 + 2).min(area.height.saturating_sub(2));
+    let rect = Rect {
+        x: area.right().saturating_sub(width + 1),
+        y: area.bottom().saturating_sub(height + 1),
+        width,
+        height,
+    };
+    f.render_widget(Clear, rect);
+    let legend = Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(
+        format!("Legend ({})", app.keymap.hint(Action::ToggleLegend)),
+    ));
+    f.render_widget(legend, rect);
+}
+
+/// Draws one box per filter down the left column and returns the space
+/// left below them.
+fn render_filter_boxes(f: &mut ratatui::Frame, app: &App, area: ratatui::layout::Rect) -> Rect {
+    let filter_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Min(0),
+        ])
+        .split(area);
+
+    let chrom = Paragraph::new(format!("CHROM: {}", app.vcf.filters.chrom))
+        .block(Block::default().borders(Borders::ALL).title("Filter"))
+        .style(Style::default().fg(Color::Green));
+    f.render_widget(chrom, filter_chunks[0]);
+
+    let ref_ = Paragraph::new(format!("REF: {}", app.vcf.filters.ref_))
+        .block(Block::default().borders(Borders::ALL))
+        .style(Style::default().fg(Color::Green));
+    f.render_widget(ref_, filter_chunks[1]);
+
+    let alt = Paragraph::new(format!("ALT: {}", app.vcf.filters.alt))
+        .block(Block::default().borders(Borders::ALL))
+        .style(Style::default().fg(Color::Green));
+    f.render_widget(alt, filter_chunks[2]);
+
+    let (pos_text, pos_color) = if app.vcf.filters.pos_invalid() {
+        (
+            format!("POS: {} (invalid POS filter)", app.vcf.filters.pos),
+            Color::Red,
+        )
+    } else {
+        (format!("POS: {}", app.vcf.filters.pos), Color::Green)
+    };
+    let pos = Paragraph::new(pos_text)
+        .block(Block::default().borders(Borders::ALL))
+        .style(Style::default().fg(pos_color));
+    f.render_widget(pos, filter_chunks[3]);
+
+    let presence = match app.vcf.filters.id_presence {
+        IdPresence::Any => "",
+        IdPresence::Novel => " (novel only)",
+        IdPresence::Known => " (known only)",
+    };
+    let id = Paragraph::new(format!("ID: {}{}", app.vcf.filters.id, presence))
+        .block(Block::default().borders(Borders::ALL))
+        .style(Style::default().fg(Color::Green));
+    f.render_widget(id, filter_chunks[4]);
+
+    let indel = Paragraph::new(format!("INDEL: {}", app.vcf.filters.indel))
+        .block(Block::default().borders(Borders::ALL))
+        .style(Style::default().fg(Color::Green));
+    f.render_widget(indel, filter_chunks[5]);
+
+    let svtype = Paragraph::new(format!("SVTYPE: {}", app.vcf.filters.svtype))
+        .block(Block::default().borders(Borders::ALL))
+        .style(Style::default().fg(Color::Green));
+    f.render_widget(svtype, filter_chunks[6]);
+
+    let flag = if app.vcf.filters.flag.is_empty() {
+        String::new()
+    } else {
+        format!(" (flag {})", app.vcf.filters.flag)
+    };
+    let info = Paragraph::new(format!("INFO: {}{}", app.vcf.filters.info, flag))
+        .block(Block::default().borders(Borders::ALL))
+        .style(Style::default().fg(Color::Green));
+    f.render_widget(info, filter_chunks[7]);
+
+    let (bed_text, bed_color) = match (&app.vcf.filters.bed, &app.vcf.bed_error) {
+        (_, Some(err)) => (format!("BED: {}", err), Color::Red),
+        (Some(bed), None) => (
+            format!(
+                "BED: {} ({}, {} regions)",
+                bed.path.file_name().unwrap_or_default().to_string_lossy(),
+                app.vcf.filters.bed_mode.label(),
+                bed.interval_count()
+            ),
+            Color::Green,
+        ),
+        (None, None) => ("BED: -".to_string(), Color::Green),
+    };
+    let bed = Paragraph::new(bed_text)
+        .block(Block::default().borders(Borders::ALL))
+        .style(Style::default().fg(bed_color));
+    f.render_widget(bed, filter_chunks[8]);
+
+    filter_chunks[9]
+}
+
+/// `pos` as a signed offset from `anchor`, e.g. `+312`.
+fn relative_pos(pos: u64, anchor: u64) -> String {
+    if pos >= anchor {
+        format!("+{}", pos - anchor)
+    } else {
+        format!("-{}", anchor - pos)
+    }
+}
+
+/// FILTER colour: green for PASS, yellow when unset, red for any failing
+/// tag (including PASS mixed with others, e.g. `PASS;q10`).
+fn filter_color(filter: &str) -> Color {
+    match filter {
+        "PASS" => Color::Green,
+        "." | "" => Color::Yellow,
+        _ => Color::Red,
+    }
+}
+
+/// Row tint in the variant list.
+fn class_color(class: AlleleClass) -> Color {
+    match class {
+        AlleleClass::Snp => Color::Cyan,
+        AlleleClass::Insertion => Color::LightGreen,
+        AlleleClass::Deletion => Color::LightRed,
+        AlleleClass::Complex => Color::LightBlue,
+        AlleleClass::Other => Color::Reset,
+    }
+}
+
+/// Lists the records around the selected one in genomic order, filters
+/// ignored; rows that fail the filters are dimmed.
+fn render_context_panel(
+    f: &mut ratatui::Frame,
+    app: &App,
+    record: Option<&VcfRecord>,
+    area: ratatui::layout::Rect,
+) {
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(format!("Context (±{})", app.context_rows));
+    let Some(selected) = record else {
+        f.render_widget(Paragraph::new("").block(block), area);
+        return;
+    };
+    let pos_range = app.vcf.filters.pos_range();
+    let lines: Vec<Line> = app
+        .context_records(selected, app.context_rows)
+        .into_iter()
+        .map(|r| {
+            let style = if std::ptr::eq(r, selected) {
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD)
+            } else if !app.vcf.filters.matches(r, &pos_range) {
+                Style::default().fg(Color::DarkGray)
+            } else {
+                Style::default()
+            };
+            let pos = app.vcf.display_pos(r);
+            let text = r
+                .sv_label(&pos)
+                .unwrap_or_else(|| format!("{}:{} {}>{}", r.chrom, pos, r.ref_, r.alt));
+            Line::styled(text, style)
+        })
+        .collect();
+    f.render_widget(Paragraph::new(lines).block(block), area);
+}
+
+/// Shortens `s` to at most `max` characters by replacing its middle with `…`.
+fn truncate_middle(s: &str, max: usize) -> String {
+    let len = s.chars().count();
+    if len <= max {
+        return s.to_string();
+    }
+    if max < 2 {
+        return "…".chars().take(max).collect();
+    }
+    let keep = max - 1;
+    let head = keep / 2;
+    let tail = keep - head;
+    let start: String = s.chars().take(head).collect();
+    let end: String = s.chars().skip(len - tail).collect();
+    format!("{}…{}", start, end)
+}
+
+/// A line across the VCF tab while the head cap hides matching records,
+/// so a capped list isn't taken for the whole result.
+fn render_truncation_banner(
+    f: &mut ratatui::Frame,
+    app: &App,
+    shown: usize,
+    matching: usize,
+    area: Rect,
+) {
+    let text = format!(
+        " ⚠ Showing the first {} of {} matching records ({} shows all) ",
+        shown,
+        matching,
+        app.keymap.hint(Action::ToggleHead)
+    );
+    let banner = Paragraph::new(text).style(
+        Style::default()
+            .fg(Color::White)
+            .bg(Color::Red)
+            .add_modifier(Modifier::BOLD),
+    );
+    f.render_widget(banner, area);
+}
+
+fn render_filter_chips(f: &mut ratatui::Frame, app: &App, area: ratatui::layout::Rect) {
+    let chip = Style::default().fg(Color::Black).bg(Color::Green);
+    let mut spans = vec![Span::raw(" Active: ")];
+    for (name, value) in app.active_filters() {
+        if spans.len() > 1 {
+            spans.push(Span::raw(" | "));
+        }
+        let sep = if value.starts_with(['<', '>', '=']) {
+            ""
+        } else {
+            "="
+        };
+        spans.push(Span::styled(format!(" {}{}{} ", name, sep, value), chip));
+    }
+    if spans.len() == 1 {
+        spans.push(Span::styled("none", Style::default().fg(Color::DarkGray)));
+    }
+    if !app.vcf.samples.is_empty() {
+        spans.push(Span::styled(
+            format!(
+                "  Samples ({}): {}",
+                app.vcf.samples.len(),
+                app.vcf.samples.join(", ")
+            ),
+            Style::default().fg(Color::Cyan),
+        ));
+    }
+    f.render_widget(Paragraph::new(Line::from(spans)), area);
+}
+
+/// Summary of the filtered records plus a variant density sparkline for one
+/// chromosome.
+fn render_stats_tab(f: &mut ratatui::Frame, app: &App, area: ratatui::layout::Rect) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(8)])
+        .split(area);
+    let top = Layout::horizontal([Constraint::Min(0), Constraint::Length(32)]).split(chunks[0]);
+
+    let filtered = app.filtered_records();
+    let stats = VariantStats::from_records(filtered.iter().copied());
+    let (biallelic, multiallelic) = app.allele_stats();
+    let ts_tv = match stats.ts_tv() {
+        Some(ratio) => format!("{:.3}", ratio),
+        None => "n/a".to_string(),
+    };
+    let mut lines = Vec::new();
+    if let Some((shown, matching)) = app.head_truncation() {
+        lines.push(Line::styled(
+            format!(
+                "⚠ The list shows the first {} of {} matching records; these stats cover all {}",
+                shown, matching, matching
+            ),
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        ));
+    }
+    lines.extend([
+        Line::from(format!("Variants: {}", stats.total)),
+        Line::from(format!(
+            "SNPs: {}  Indels: {}  Other: {}",
+            stats.snps, stats.indels, stats.other
+        )),
+        Line::from(format!(
+            "Ts/Tv: {} ({} transitions, {} transversions)",
+            ts_tv, stats.transitions, stats.transversions
+        )),
+        Line::from(format!(
+            "Biallelic: {}  Multi-allelic: {}",
+            biallelic, multiallelic
+        )),
+        Line::from(""),
+    ]);
+    let span = app.span();
+    lines.extend(stats.per_chrom.iter().map(|(chrom, count)| {
+        let range = match span.iter().find(|(c, _, _)| c == chrom) {
+            Some((_, min, max)) => format!("{}:{}-{}", chrom, min, max),
+            None => String::new(),
+        };
+        Line::from(format!("{:<12} {:<8} {}", chrom, count, range))
+    }));
+    if !app.vcf.samples.is_empty() {
+        lines.push(Line::from(""));
+        lines.push(Line::styled(
+            "Genotypes (hom-ref / het / hom-alt / missing):",
+            Style::default().fg(Color::Cyan),
+        ));
+        lines.extend(app.vcf.samples.iter().enumerate().map(|(i, sample)| {
+            let gt = GtCounts::from_records(filtered.iter().copied(), i);
+            Line::from(format!(
+                "{:<12} {:<8} {:<8} {:<8} {}",
+                sample, gt.hom_ref, gt.het, gt.hom_alt, gt.missing
+            ))
+        }));
+    }
+    if !app.vcf.warnings.is_empty() {
+        lines.push(Line::from(""));
+        lines.push(Line::styled(
+            format!("Parse warnings ({}):", app.vcf.warnings.len()),
+            Style::default().fg(Color::Red),
+        ));
+        lines.extend(app.vcf.warnings.iter().map(|w| Line::from(w.as_str())));
+    }
+    if !app.vcf.skipped.is_empty() {
+        lines.push(Line::from(""));
+        lines.push(Line::styled(
+            format!("Skipped lines ({}):", app.vcf.skipped.len()),
+            Style::default().fg(Color::DarkGray),
+        ));
+        lines.extend(
+            app.vcf
+                .skipped
+                .iter()
+                .map(|s| Line::styled(s.as_str(), Style::default().fg(Color::DarkGray))),
+        );
+    }
+    let summary = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title("Summary (filtered records)"),
+    );
+    f.render_widget(summary, top[0]);
+
+    let total = app.vcf.records.len().max(1) as f64;
+    let rows: Vec<Row> = app
+        .filter_counts()
+        .into_iter()
+        .map(|(tag, count)| {
+            let color = filter_color(&tag);
+            Row::new([
+                Cell::from(Span::styled(tag, Style::default().fg(color))),
+                Cell::from(count.to_string()),
+                Cell::from(format!("{:.1}%", count as f64 * 100.0 / total)),
+            ])
+        })
+        .collect();
+    let filters = Table::new(
+        rows,
+        [
+            Constraint::Min(8),
+            Constraint::Length(9),
+            Constraint::Length(6),
+        ],
+    )
+    .header(
+        Row::new(["FILTER", "Records", "Share"]).style(
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        ),
+    )
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title("FILTER tags (all records)"),
+    );
+    f.render_widget(filters, top[1]);
+
+    let bins = (chunks[1].width.saturating_sub(2) as usize).min(100);
+    let (title, data) = match app.stats_chrom() {
+        Some(chrom) => (
+            format!(
+                "Density on {} ({} windows, {}/{} switch chromosome)",
+                chrom,
+                bins,
+                app.keymap.hint(Action::PrevChrom),
+                app.keymap.hint(Action::NextChrom)
+            ),
+            app.density(&chrom, bins),
+        ),
+        None => ("Density".to_string(), Vec::new()),
+    };
+    let sparkline = Sparkline::default()
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .data(&data)
+        .style(Style::default().fg(Color::Green));
+    f.render_widget(sparkline, chunks[1]);
+}
+
+/// Renders the selected record with INFO split into one wrapped line per
+/// entry, and returns how far the panel can be scrolled.
+fn render_detail_panel(
+    f: &mut ratatui::Frame,
+    app: &App,
+    record: Option<&VcfRecord>,
+    area: ratatui::layout::Rect,
+) -> u16 {
+    let block = if app.vcf.detail_focused {
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Yellow))
+            .title(format!(
+                "Detail ({}/{} scroll, {}/{} back)",
+                app.keymap.hint(Action::MoveDown),
+                app.keymap.hint(Action::MoveUp),
+                app.keymap.hint(Action::FocusDetail),
+                app.keymap.hint(Action::Back)
+            ))
+    } else {
+        Block::default().borders(Borders::ALL).title(format!(
+            "Detail ({} = focus)",
+            app.keymap.hint(Action::FocusDetail)
+        ))
+    };
+    let Some(r) = record else {
+        f.render_widget(Paragraph::new("No variant selected").block(block), area);
+        return 0;
+    };
+
+    let label = |name: String| Span::styled(name, Style::default().fg(Color::Cyan));
+    let mut lines = vec![
+        Line::from(vec![label("CHROM: ".into()), Span::raw(r.chrom.as_str())]),
+        Line::from(vec![
+            label(format!("POS ({}): ", app.vcf.coordinate_label())),
+            Span::raw(app.vcf.display_pos(r)),
+        ]),
+        Line::from(vec![
+            label("ID: ".into()),
+            Span::raw(shown_value(app, &r.id)),
+        ]),
+        Line::from(vec![label("REF: ".into()), Span::raw(r.ref_.as_str())]),
+        Line::from(vec![label("ALT: ".into()), Span::raw(r.alt.as_str())]),
+        Line::from(vec![label("QUAL: ".into()), Span::raw(r.qual_text())]),
+        Line::from(vec![label("FILTER: ".into()), Span::raw(r.filter.as_str())]),
+    ];
+    if let Some(svtype) = r.sv_type() {
+        lines.push(Line::from(vec![
+            label("SVTYPE: ".into()),
+            Span::raw(svtype),
+        ]));
+        if let Some(end) = r.sv_end() {
+            lines.push(Line::from(vec![
+                label("END: ".into()),
+                Span::raw(end.to_string()),
+            ]));
+        }
+    }
+    if let Some(problem) = r.problem {
+        lines.push(Line::from(Span::styled(
+            format!("⚠ {}", problem),
+            Style::default().fg(Color::Red),
+        )));
+    }
+    if let Some(source) = &r.source {
+        lines.insert(
+            0,
+            Line::from(vec![
+                label("SOURCE: ".into()),
+                Span::raw(source.display().to_string()),
+            ]),
+        );
+    }
+    let afs = r.allele_frequencies();
+    for (i, af) in afs.iter().enumerate() {
+        const BAR: usize = 20;
+        // An unparsable value gets no bar but keeps the later indexes.
+        let Some(af) = af else {
+            continue;
+        };
+        let filled = (af * BAR as f64).ceil() as usize;
+        let name = if afs.len() > 1 {
+            format!("AF[{}]: ", i + 1)
+        } else {
+            "AF: ".to_string()
+        };
+        lines.push(Line::from(vec![
+            label(name),
+            Span::styled("█".repeat(filled), Style::default().fg(Color::Green)),
+            Span::styled(
+                "░".repeat(BAR - filled),
+                Style::default().fg(Color::DarkGray),
+            ),
+            Span::raw(format!(" {}", af)),
+        ]));
+    }
+    if r.info == "." || r.info.is_empty() {
+        lines.push(Line::from(vec![
+            label("INFO: ".into()),
+            Span::raw(r.info.as_str()),
+        ]));
+    } else {
+        lines.push(Line::from(label("INFO:".into())));
+        for entry in r.info.split(';') {
+            // Number=A/R values go one per allele when the header says so.
+            let per_allele = entry.split_once('=').and_then(|(key, value)| {
+                let number = app.vcf.info_numbers.get(key)?;
+                Some((key, r.allele_values(number, value)?))
+            });
+            match per_allele {
+                Some((key, values)) => {
+                    lines.push(Line::from(format!("  {}:", key)));
+                    lines.extend(values.into_iter().map(|(allele, value)| {
+                        Line::from(format!("    {}: {}", allele, shown_value(app, value)))
+                    }));
+                }
+                None => lines.push(Line::from(format!("  {}", shown_value(app, entry)))),
+            }
+        }
+    }
+    if !r.genotypes.is_empty() {
+        lines.push(Line::from(label(format!(
+            "GT ({} = {}):",
+            app.keymap.hint(Action::ToggleAlleles),
+            if app.vcf.gt_alleles {
+                "indexes"
+            } else {
+                "alleles"
+            }
+        ))));
+        lines.extend(r.genotypes.iter().enumerate().map(|(i, gt)| {
+            let name = app.vcf.samples.get(i).map_or("?", String::as_str);
+            if app.vcf.gt_alleles {
+                Line::from(format!("  {}: {}", name, r.gt_alleles(gt)))
+            } else {
+                Line::from(format!("  {}: {}", name, gt))
+            }
+        }));
+    }
+
+    let max_scroll = overflow(&lines, area);
+    let detail = Paragraph::new(lines)
+        .block(block)
+        .wrap(Wrap { trim: false })
+        .scroll((app.vcf.detail_scroll.min(max_scroll), 0));
+    f.render_widget(detail, area);
+    max_scroll
+}
+
+/// A field value for the detail panel: percent-decoded when enabled, with
+/// decoded tabs and newlines drawn as `⇥` and `↵` so the line stays whole.
+fn shown_value<'a>(app: &App, value: &'a str) -> Cow<'a, str> {
+    if !app.percent_decode {
+        return Cow::Borrowed(value);
+    }
+    match percent_decode(value) {
+        Cow::Owned(decoded) => Cow::Owned(decoded.replace('\t', "⇥").replace('\n', "↵")),
+        borrowed => borrowed,
+    }
+}
+
+fn render_load_progress(f: &mut ratatui::Frame, path: &Path, done: u64, total: u64) {
+    let row = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(0),
+            Constraint::Length(3),
+            Constraint::Min(0),
+        ])
+        .split(f.area())[1];
+    let area = centered_rect(60, 100, row);
+    let ratio = if total == 0 {
+        1.0
+    } else {
+        (done as f64 / total as f64).min(1.0)
+    };
+    let gauge = Gauge::default()
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(format!("Loading {}", path.display())),
+        )
+        .gauge_style(Style::default().fg(Color::Cyan))
+        .ratio(ratio);
+    f.render_widget(Clear, area);
+    f.render_widget(gauge, area);
+}
+
+/// Stands in for the status bar while an export runs in the background.
+fn render_export_progress(f: &mut ratatui::Frame, app: &App, job: &ExportJob, area: Rect) {
+    let written = job.written.load(Ordering::Relaxed).min(job.total);
+    let gauge = Gauge::default()
+        .gauge_style(Style::default().fg(Color::Cyan).bg(Color::Black))
+        .ratio(written as f64 / job.total.max(1) as f64)
+        .label(format!(
+            "Exporting to {}: {}/{} records ({} cancels)",
+            job.out.display(),
+            written,
+            job.total,
+            app.keymap.hint(Action::Back)
+        ));
+    f.render_widget(gauge, area);
+}
+
+fn render_status_bar(f: &mut ratatui::Frame, app: &App, area: ratatui::layout::Rect) {
+    if let Some(job) = &app.export {
+        render_export_progress(f, app, job, area);
+        return;
+    }
+    let case = if app.vcf.filters.case_sensitive {
+        "case-sensitive"
+    } else {
+        "case-insensitive"
+    };
+    let rows = if app.vcf.show_dimmed {
+        "non-matching dimmed"
+    } else {
+        "non-matching hidden"
+    };
+    let keys = &app.keymap;
+    let hints = match &app.message {
+        Some((message, _)) => message.clone(),
+        None => format!(
+            "{} case, {} dim/hide, {} BED mode, {} 0/1-based, {} report",
+            keys.hint(Action::ToggleCase),
+            keys.hint(Action::ToggleDimmed),
+            keys.hint(Action::ToggleBed),
+            keys.hint(Action::ToggleZeroBased),
+            keys.hint(Action::WriteReport)
+        ),
+    };
+    let mut spans = Vec::new();
+    if app.vcf.filters.pos_invalid() {
+        spans.push(Span::styled(
+            " invalid POS filter ",
+            Style::default().fg(Color::White).bg(Color::Red),
+        ));
+    }
+    spans.push(Span::raw(format!(
+        " {} | {} | POS {} | {}",
+        case,
+        rows,
+        app.vcf.coordinate_label(),
+        hints
+    )));
+    let status =
+        Paragraph::new(Line::from(spans)).style(Style::default().fg(Color::Black).bg(Color::Cyan));
+    f.render_widget(status, area);
+}
+
+/// Draws the open modal and returns how far it can be scrolled.
+fn render_modal(f: &mut ratatui::Frame, modal: &ModalState, app: &App) -> u16 {
+    if modal.kind == ModalKind::Record {
+        return render_record_modal(f, modal, app);
+    }
+    let area = centered_rect(60, 30, f.area());
+    f.render_widget(Clear, area);
+
+    match modal.kind {
+        ModalKind::Menu => {
+            let list_items: Vec<ListItem> = MENU_ITEMS
+                .iter()
+                .enumerate()
+                .map(|(i, item)| {
+                    let style = if i == modal.menu_selected {
+                        Style::default()
+                            .fg(Color::Yellow)
+                            .add_modifier(Modifier::BOLD)
+                    } else {
+                        Style::default()
+                    };
+                    let text = match app.filter_value(*item) {
+                        Some(value) => format!("{} = {}", item.label(), value),
+                        None => item.label().to_string(),
+                    };
+                    ListItem::new(Line::from(Span::styled(text, style)))
+                })
+                .collect();
+
+            let list = List::new(list_items)
+                .block(
+                    Block::default()
+                        .title("Filter Menu (Up/Down, Enter edit, Del clear)")
+                        .borders(Borders::ALL),
+                )
+                .highlight_style(Style::default().bg(Color::DarkGray));
+
+            let mut state = ListState::default();
+            state.select(Some(modal.menu_selected));
+            f.render_stateful_widget(list, area, &mut state);
+        }
+        ModalKind::Export => {
+            let list_items: Vec<ListItem> = EXPORT_ITEMS
+                .iter()
+                .map(|item| {
+                    let text = match item {
+                        ExportItem::Filtered | ExportItem::Bed => {
+                            format!("{} ({})", item.label(), app.filtered_records().len())
+                        }
+                        ExportItem::Bookmarked => {
+                            format!("{} ({})", item.label(), app.vcf.bookmarks.len())
+                        }
+                        ExportItem::Cancel => item.label().to_string(),
+                    };
+                    ListItem::new(text)
+                })
+                .collect();
+            let list = List::new(list_items)
+                .block(
+                    Block::default()
+                        .title("Export (Up/Down, Enter export)")
+                        .borders(Borders::ALL),
+                )
+                .highlight_style(
+                    Style::default()
+                        .fg(Color::Yellow)
+                        .add_modifier(Modifier::BOLD),
+                );
+            let mut state = ListState::default();
+            state.select(Some(modal.menu_selected));
+            f.render_stateful_widget(list, area, &mut state);
+        }
+        ModalKind::Recent => {
+            let list_items: Vec<ListItem> = if app.recent.is_empty() {
+                vec![ListItem::new("No recently opened files")]
+            } else {
+                app.recent
+                    .iter()
+                    .map(|path| ListItem::new(path.display().to_string()))
+                    .collect()
+            };
+            let list = List::new(list_items)
+                .block(
+                    Block::default()
+                        .title("Recent files (Up/Down, Enter open, Esc close)")
+                        .borders(Borders::ALL),
+                )
+                .highlight_style(
+                    Style::default()
+                        .fg(Color::Yellow)
+                        .add_modifier(Modifier::BOLD),
+                );
+            let mut state = ListState::default();
+            state.select((!app.recent.is_empty()).then_some(modal.menu_selected));
+            f.render_stateful_widget(list, area, &mut state);
+        }
+        ModalKind::ConfirmQuit => {
+            let text = vec![
+                Line::from("Active filters and bookmarks will be lost."),
+                Line::from(""),
+                Line::from("Quit? (y/n)"),
+            ];
+            let prompt = Paragraph::new(text)
+                .style(Style::default().fg(Color::Yellow))
+                .block(Block::default().title("Confirm quit").borders(Borders::ALL));
+            f.render_widget(prompt, area);
+        }
+        ModalKind::Columns => {
+            let list_items: Vec<ListItem> = ALL_COLUMNS
+                .iter()
+                .map(|c| {
+                    let mark = if app.vcf.visible_columns.contains(c) {
+                        "[x]"
+                    } else {
+                        "[ ]"
+                    };
+                    let required = if app.vcf.filters.non_missing.contains(c) {
+                        "  (non-missing)"
+                    } else {
+                        ""
+                    };
+                    ListItem::new(format!("{} {}{}", mark, c.label(), required))
+                })
+                .collect();
+
+            let list = List::new(list_items)
+                .block(
+                    Block::default()
+                        .title("Columns (Up/Down, Space toggle, n require non-missing, Esc close)")
+                        .borders(Borders::ALL),
+                )
+                .highlight_style(
+                    Style::default()
+                        .fg(Color::Yellow)
+                        .add_modifier(Modifier::BOLD),
+                );
+
+            let mut state = ListState::default();
+            state.select(Some(modal.menu_selected));
+            f.render_stateful_widget(list, area, &mut state);
+        }
+        _ => {
+            let title = match modal.kind {
+                ModalKind::Chrom => {
+                    "CHROM filter, a,b matches either, !x excludes (Esc cancel, Enter accept)"
+                }
+                ModalKind::Ref => "REF filter, !x excludes (Esc cancel, Enter accept)",
+                ModalKind::Alt => "ALT filter, !x excludes (Esc cancel, Enter accept)",
+                ModalKind::Id => "ID filter, !x excludes (Esc cancel, Enter accept)",
+                ModalKind::Pos => {
+                    "POS filter: 12345, 1000-5000, 1000- or -5000 (Esc cancel, Enter accept)"
+                }
+                ModalKind::IndelLen => {
+                    "Indel size |len(ALT)-len(REF)|, e.g. >=5 (Esc cancel, Enter accept)"
+                }
+                ModalKind::SvType => {
+                    "SVTYPE filter, e.g. DEL,DUP; !x excludes (Esc cancel, Enter accept)"
+                }
+                ModalKind::Info => {
+                    "INFO filter: DP>=10, DB or GENE=x; Tab completes (Esc cancel, Enter accept)"
+                }
+                ModalKind::Flag => {
+                    "INFO flag key, e.g. SOMATIC; a,b matches either, !x excludes, Tab completes (Esc cancel, Enter accept)"
+                }
+                ModalKind::Bed => "BED file path (Esc cancel, Enter load)",
+                ModalKind::Region => {
+                    "Region, e.g. chr1:1,000-5,000 or chr1; sets CHROM and POS (Esc cancel, Enter accept)"
+                }
+                ModalKind::Command => {
+                    "Command, e.g. sort qual desc; Tab completes (Esc cancel, Enter run)"
+                }
+                ModalKind::GroupBy => {
+                    "Group by INFO key, e.g. SVTYPE; empty ungroups, Tab completes (Esc cancel, Enter accept)"
+                }
+                _ => unreachable!(),
+            };
+            let mut block = Block::default().title(title).borders(Borders::ALL);
+            if let Some(count) = modal.match_count {
+                block = block.title_bottom(
+                    Line::from(format!(
+                        " {} of {} records match ",
+                        count,
+                        app.vcf.records.len()
+                    ))
+                    .right_aligned(),
+                );
+            }
+            let mut lines = vec![Line::from(modal.input.as_str())];
+            let suggestions = match modal.kind {
+                ModalKind::Info | ModalKind::Flag | ModalKind::GroupBy => {
+                    info_suggestions(&app.vcf.info_keys, &modal.input)
+                }
+                ModalKind::Command => command_suggestions(&modal.input),
+                _ => Vec::new(),
+            };
+            if !suggestions.is_empty() {
+                lines.push(Line::from(Span::styled(
+                    suggestions.join("  "),
+                    Style::default().fg(Color::DarkGray),
+                )));
+            }
+            let inner = block.inner(area);
+            let input = Paragraph::new(lines)
+                .style(Style::default().fg(Color::Cyan))
+                .wrap(Wrap { trim: false })
+                .block(block);
+            f.render_widget(input, area);
+
+            // The terminal's own cursor at the editing position. A value
+            // that overflows is assumed to wrap at the box edge, as one
+            // without spaces does.
+            let typed = Line::from(&modal.input[..modal.cursor_offset()]).width() as u16;
+            if let Some(row) = typed.checked_div(inner.width)
+                && row < inner.height
+            {
+                f.set_cursor_position(Position {
+                    x: inner.x + typed % inner.width,
+                    y: inner.y + row,
+                });
+            }
+        }
+    }
+    0
+}
+
+/// Lines `lines` overflow a bordered `area` by once wrapped to its width,
+/// i.e. how far it can be scrolled.
+fn overflow(lines: &[Line], area: Rect) -> u16 {
+    let inner_width = area.width.saturating_sub(2).max(1) as usize;
+    let inner_height = area.height.saturating_sub(2) as usize;
+    let wrapped: usize = lines
+        .iter()
+        .map(|l| l.width().div_ceil(inner_width).max(1))
+        .sum();
+    wrapped.saturating_sub(inner_height) as u16
+}
+
+/// The selected record with every field in full, raw as in the file, for
+/// values the table and detail panel cut short. Returns the scroll limit.
+fn render_record_modal(f: &mut ratatui::Frame, modal: &ModalState, app: &App) -> u16 {
+    let area = centered_rect(80, 80, f.area());
+    f.render_widget(Clear, area);
+    let Some(r) = app.selected_record() else {
+        return 0;
+    };
+    let label = |name: &str| Span::styled(format!("{}: ", name), Style::default().fg(Color::Cyan));
+    let source = r.source.as_ref().map(|p| p.display().to_string());
+    let fields = [
+        ("SOURCE", source.unwrap_or_default()),
+        ("CHROM", r.chrom.clone()),
+        ("POS", app.vcf.display_pos(r)),
+        ("ID", r.id.clone()),
+        ("REF", r.ref_.clone()),
+        ("ALT", r.alt.clone()),
+        ("QUAL", r.qual_text().to_string()),
+        ("FILTER", r.filter.clone()),
+        ("INFO", r.info.clone()),
+    ];
+    let mut lines: Vec<Line> = fields
+        .into_iter()
+        .filter(|(name, value)| *name != "SOURCE" || !value.is_empty())
+        .map(|(name, value)| Line::from(vec![label(name), Span::raw(value)]))
+        .collect();
+    if !r.genotypes.is_empty() {
+        lines.push(Line::from(label("GT")));
+        lines.extend(r.genotypes.iter().enumerate().map(|(i, gt)| {
+            let name = app.vcf.samples.get(i).map_or("?", String::as_str);
+            Line::from(format!("  {}: {}", name, gt))
+        }));
+    }
+
+    let max_scroll = overflow(&lines, area);
+    let title = format!(
+        "Record at line {} (Up/Down scroll, Esc close){}",
+        r.line,
+        if max_scroll > 0 {
+            format!(" [{}/{}]", modal.scroll.min(max_scroll), max_scroll)
+        } else {
+            String::new()
+        }
+    );
+    let text = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .wrap(Wrap { trim: false })
+        .scroll((modal.scroll.min(max_scroll), 0));
+    f.render_widget(text, area);
+    max_scroll
+}
+
+/// Cuts `line` to `width` characters, ending it with `…` if anything was
+/// cut so a clipped table cell doesn't pass for the whole value.
+fn ellipsize(line: Line<'static>, width: usize) -> Line<'static> {
+    if width == 0
+        || line
+            .spans
+            .iter()
+            .map(|s| s.content.chars().count())
+            .sum::<usize>()
+            <= width
+    {
+        return line;
+    }
+    let mut room = width - 1;
+    let mut spans = Vec::new();
+    for span in line.spans {
+        if room == 0 {
+            break;
+        }
+        let text: String = span.content.chars().take(room).collect();
+        room -= text.chars().count();
+        spans.push(Span::styled(text, span.style));
+    }
+    spans.push(Span::raw("…"));
+    Line::from(spans)
+}
+
+/// Splits `value` into lines of at most `width` characters.
+fn wrap_chars(value: &str, width: usize) -> Vec<Line<'static>> {
+    let chars: Vec<char> = value.chars().collect();
+    if chars.is_empty() {
+        return vec![Line::from("")];
+    }
+    chars
+        .chunks(width.max(1))
+        .map(|chunk| Line::from(chunk.iter().collect::<String>()))
+        .collect()
+}
+
+fn centered_rect(
+    percent_x: u16,
+    percent_y: u16,
+    r: ratatui::layout::Rect,
+) -> ratatui::layout::Rect {
+    let popup_layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage((100 - percent_y) / 2),
+            Constraint::Percentage(percent_y),
+            Constraint::Percentage((100 - percent_y) / 2),
+        ])
+        .split(r);
+
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - percent_x) / 2),
+            Constraint::Percentage(percent_x),
+            Constraint::Percentage((100 - percent_x) / 2),
+        ])
+        .split(popup_layout[1])[1]
+}
+
+/// Loads the selected file, drawing a progress gauge while it parses.
+pub fn load_with_progress<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) {
+    let Some(path) = app.selected_file().cloned() else {
+        return;
+    };
+    let no_color = app.no_color;
+    let mut draw = |done: u64, total: u64| {
+        let _ = terminal.draw(|f| {
+            render_load_progress(f, &path, done, total);
+            if no_color {
+                strip_colors(f.buffer_mut());
+            }
+        });
+    };
+    app.load_selected_vcf(Some(&mut draw));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vcf::parse_vcf_reader;
+    use std::io::Cursor;
+
+    #[test]
+    fn ui_survives_tiny_terminals() {
+        use ratatui::backend::TestBackend;
+
+        let data = "20\t14370\trs6054257\tG\tA\t29\tPASS\tNS=3;DP=14\n";
+        let mut app = App::default();
+        app.tabs.titles = vec!["Files".to_owned(), "VCF Viewer".to_owned()];
+        app.vcf.visible_columns = ALL_COLUMNS.to_vec();
+        app.vcf.records = parse_vcf_reader(Cursor::new(data)).unwrap().records.into();
+        app.vcf.selected = Some(0);
+
+        for (w, h) in [(0, 0), (1, 1), (4, 2), (12, 5), (30, 8)] {
+            let mut terminal = Terminal::new(TestBackend::new(w, h)).unwrap();
+            for tab in 0..2 {
+                app.tabs.index = tab;
+                for modal in [None, Some(ModalState::new_menu())] {
+                    app.modal = modal;
+                    terminal.draw(|f| ui(f, &mut app)).unwrap();
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,1527 @@
+//! VCF/BCF records and parsing, plus the BED and POS helpers filters build on.
+
+use flate2::read::MultiGzDecoder;
+use noodles_bcf as bcf;
+use noodles_vcf::{self as vcf, variant::io::Write as _};
+use std::{
+    borrow::Cow,
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    fs::File,
+    io,
+    io::{BufRead, BufReader, Read, Seek, SeekFrom, Write},
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{self, AtomicBool},
+    },
+};
+
+#[derive(Debug, Clone)]
+pub struct VcfRecord {
+    pub chrom: String,
+    pub pos: u64,
+    pub id: String,
+    pub ref_: String,
+    pub alt: String,
+    pub qual: Option<f64>, // None for `.` (or text that isn't a number)
+    pub qual_raw: String,  // QUAL as written, so output keeps e.g. `29.0`
+    pub filter: String,
+    pub info: String,
+    pub genotypes: Vec<String>, // GT of each sample column; empty without a FORMAT GT key
+    pub sample_columns: String, // FORMAT and the sample columns, tab-joined as read, for export
+    pub source: Option<Arc<Path>>, // originating file in the combined view
+    pub line: usize,            // 1-based line in the VCF text (for BCF, its decoded form)
+    pub problem: Option<&'static str>, // set by `validate_record`; still displayed
+}
+
+/// Checks the fields a malformed writer tends to get wrong. ALT `.` is a
+/// valid reference-only site, so only a blank ALT is flagged.
+fn validate_record(pos: u64, ref_: &str, alt: &str) -> Option<&'static str> {
+    if ref_.is_empty() || ref_ == "." {
+        Some("REF is empty")
+    } else if alt.is_empty() {
+        Some("ALT is empty")
+    } else if pos == 0 {
+        Some("POS is not a positive integer")
+    } else if !ref_
+        .bytes()
+        .all(|b| matches!(b.to_ascii_uppercase(), b'A' | b'C' | b'G' | b'T' | b'N'))
+    {
+        Some("REF has characters other than ACGTN")
+    } else {
+        None
+    }
+}
+
+impl VcfRecord {
+    /// QUAL as it appeared in the file.
+    pub fn qual_text(&self) -> &str {
+        &self.qual_raw
+    }
+
+    /// SNP if every ALT is a single base against a single-base REF, indel if
+    /// any ALT changes the length, otherwise (MNPs, symbolic alleles) other.
+    fn variant_type(&self) -> VariantType {
+        let alts: Vec<&str> = self
+            .alt
+            .split(',')
+            .filter(|a| !a.starts_with('<') && *a != "." && *a != "*")
+            .collect();
+        if alts.is_empty() {
+            VariantType::Other
+        } else if self.ref_.len() == 1 && alts.iter().all(|a| a.len() == 1) {
+            VariantType::Snp
+        } else if self.indel_length() > 0 {
+            VariantType::Indel
+        } else {
+            VariantType::Other
+        }
+    }
+
+    /// Like `variant_type`, but with indels split by direction; MNPs and
+    /// sites whose ALTs are of different kinds are `Complex`.
+    pub fn allele_class(&self) -> AlleleClass {
+        let mut classes = self
+            .alt
+            .split(',')
+            .filter(|a| !a.starts_with('<') && *a != "." && *a != "*")
+            .map(|a| match a.len().cmp(&self.ref_.len()) {
+                Ordering::Greater => AlleleClass::Insertion,
+                Ordering::Less => AlleleClass::Deletion,
+                Ordering::Equal if a.len() == 1 => AlleleClass::Snp,
+                Ordering::Equal => AlleleClass::Complex,
+            });
+        let Some(first) = classes.next() else {
+            return AlleleClass::Other;
+        };
+        if classes.all(|c| c == first) {
+            first
+        } else {
+            AlleleClass::Complex
+        }
+    }
+
+    /// `gt` with each allele index replaced by its REF (0) or ALT allele,
+    /// e.g. `0|1` -> `A|T`. Missing (`.`) and out-of-range indexes are kept.
+    pub fn gt_alleles(&self, gt: &str) -> String {
+        let alleles: Vec<&str> = std::iter::once(self.ref_.as_str())
+            .chain(self.alt.split(','))
+            .collect();
+        let mut out = String::new();
+        for part in gt.split_inclusive(['/', '|']) {
+            let (index, sep) = match part.strip_suffix(['/', '|']) {
+                Some(index) => (index, &part[index.len()..]),
+                None => (part, ""),
+            };
+            let allele = index.parse::<usize>().ok().and_then(|n| alleles.get(n));
+            out.push_str(allele.copied().unwrap_or(index));
+            out.push_str(sep);
+        }
+        out
+    }
+
+    /// Values of INFO `AF`, one per ALT allele in ALT order. Values that
+    /// don't parse or fall outside 0-1 (e.g. `.`) are `None`, keeping the
+    /// later ones at their allele's index.
+    pub fn allele_frequencies(&self) -> Vec<Option<f64>> {
+        self.info
+            .split(';')
+            .find_map(|entry| entry.strip_prefix("AF="))
+            .map(|afs| {
+                afs.split(',')
+                    .map(|v| v.parse::<f64>().ok().filter(|v| (0.0..=1.0).contains(v)))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// More than one ALT allele at this site.
+    pub fn is_multiallelic(&self) -> bool {
+        self.alt.contains(',')
+    }
+
+    /// Pairs each value of an INFO field declared `Number=A` (one per ALT)
+    /// or `Number=R` (REF, then each ALT) with its allele. None for other
+    /// numbers, a single allele, or values that don't line up.
+    pub fn allele_values<'a>(
+        &'a self,
+        number: &str,
+        value: &'a str,
+    ) -> Option<Vec<(&'a str, &'a str)>> {
+        let alts = self.alt.split(',');
+        let alleles: Vec<&str> = match number {
+            "A" => alts.collect(),
+            "R" => std::iter::once(self.ref_.as_str()).chain(alts).collect(),
+            _ => return None,
+        };
+        let values: Vec<&str> = value.split(',').collect();
+        (alleles.len() > 1 && values.len() == alleles.len())
+            .then(|| alleles.into_iter().zip(values).collect())
+    }
+
+    /// Largest `|len(ALT) - len(REF)|` across the ALT alleles. Symbolic and
+    /// missing alleles are ignored.
+    pub fn indel_length(&self) -> usize {
+        self.alt
+            .split(',')
+            .filter(|a| !a.starts_with('<') && *a != "." && *a != "*")
+            .map(|a| a.len().abs_diff(self.ref_.len()))
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Raw value of an INFO key, or `None` if absent. Flags give `Some("")`.
+    pub fn info_value(&self, key: &str) -> Option<&str> {
+        // Scanned in place: this runs per record when filtering and grouping.
+        self.info
+            .split(';')
+            .find_map(|entry| match entry.split_once('=') {
+                Some((k, v)) => (k == key).then_some(v),
+                None => (entry == key && !entry.is_empty() && entry != ".").then_some(""),
+            })
+    }
+
+    /// Structural variant type from INFO `SVTYPE`, falling back to a symbolic
+    /// ALT such as `<DEL>` or `<DUP:TANDEM>` (which gives `DUP`).
+    pub fn sv_type(&self) -> Option<&str> {
+        if let Some(t) = self.info_value("SVTYPE").filter(|t| !t.is_empty()) {
+            return Some(t);
+        }
+        let symbolic = self.alt.strip_prefix('<')?.split(',').next()?;
+        let inner = symbolic.strip_suffix('>')?;
+        Some(inner.split(':').next().unwrap_or(inner))
+    }
+
+    /// End coordinate of an SV from INFO `END`, else `POS + |SVLEN|`.
+    pub fn sv_end(&self) -> Option<u64> {
+        if let Some(end) = self.info_value("END").and_then(|v| v.parse().ok()) {
+            return Some(end);
+        }
+        let len: i64 = self.info_value("SVLEN")?.split(',').next()?.parse().ok()?;
+        Some(self.pos + len.unsigned_abs())
+    }
+
+    /// `chrom:pos-END SVTYPE` for structural variants, `None` otherwise.
+    pub fn sv_label(&self, pos: &str) -> Option<String> {
+        let svtype = self.sv_type()?;
+        Some(match self.sv_end() {
+            Some(end) => format!("{}:{}-{} {}", self.chrom, pos, end, svtype),
+            None => format!("{}:{} {}", self.chrom, pos, svtype),
+        })
+    }
+}
+
+/// Per-chromosome blocks of a coordinate-sorted file, so POS queries can
+/// binary-search into each block instead of scanning every record. Only
+/// built when each chromosome is contiguous and its positions never decrease.
+#[derive(Debug, Default)]
+pub struct PosIndex {
+    pub blocks: Vec<(String, Range<usize>)>,
+    pub positions: Vec<u64>,
+}
+
+impl PosIndex {
+    pub fn build(records: &[VcfRecord]) -> Option<Self> {
+        let mut blocks: Vec<(String, Range<usize>)> = Vec::new();
+        let mut positions = Vec::with_capacity(records.len());
+        let mut seen = HashSet::new();
+
+        for (i, r) in records.iter().enumerate() {
+            let pos = r.pos;
+            match blocks.last_mut() {
+                Some((chrom, range)) if *chrom == r.chrom => {
+                    if pos < positions[i - 1] {
+                        return None;
+                    }
+                    range.end = i + 1;
+                }
+                _ => {
+                    if !seen.insert(r.chrom.clone()) {
+                        return None;
+                    }
+                    blocks.push((r.chrom.clone(), i..i + 1));
+                }
+            }
+            positions.push(pos);
+        }
+
+        Some(PosIndex { blocks, positions })
+    }
+
+    /// Record index ranges whose POS lies within `start..=end`.
+    pub fn ranges(&self, start: u64, end: u64) -> Vec<Range<usize>> {
+        self.blocks
+            .iter()
+            .filter_map(|(_, block)| {
+                let slice = &self.positions[block.clone()];
+                let lo = slice.partition_point(|&p| p < start);
+                let hi = slice.partition_point(|&p| p <= end);
+                (lo < hi).then(|| block.start + lo..block.start + hi)
+            })
+            .collect()
+    }
+}
+
+/// Regions loaded from a BED file, kept per chromosome as sorted, merged
+/// 0-based half-open intervals.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct BedRegions {
+    pub path: PathBuf,
+    regions: HashMap<String, Vec<(u64, u64)>>,
+}
+
+impl BedRegions {
+    pub fn interval_count(&self) -> usize {
+        self.regions.values().map(Vec::len).sum()
+    }
+
+    /// `pos` is a 1-based VCF position.
+    pub fn contains(&self, chrom: &str, pos: u64) -> bool {
+        let Some(intervals) = self.regions.get(chrom) else {
+            return false;
+        };
+        let Some(zero_based) = pos.checked_sub(1) else {
+            return false;
+        };
+        let idx = intervals.partition_point(|&(start, _)| start <= zero_based);
+        idx > 0 && zero_based < intervals[idx - 1].1
+    }
+}
+
+/// Wraps a reader and reports `(bytes read, total bytes)` each time another
+/// whole percent of the input has been consumed.
+pub struct ProgressReader<'a, R> {
+    pub inner: R,
+    read: u64,
+    pub total: u64,
+    last_percent: u64,
+    callback: &'a mut dyn FnMut(u64, u64),
+}
+
+impl<R: Read> Read for ProgressReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.read += n as u64;
+        let percent = (self.read * 100).checked_div(self.total).unwrap_or(100);
+        if percent != self.last_percent {
+            self.last_percent = percent;
+            (self.callback)(self.read, self.total);
+        }
+        Ok(n)
+    }
+}
+
+/// Records from one parse, plus notes about lines that were skipped or
+/// had fields that could not be read.
+#[derive(Debug, Default)]
+pub struct ParsedVcf {
+    pub header: Vec<String>, // `#` lines, kept for writing records back out
+    pub records: Vec<VcfRecord>,
+    pub warnings: Vec<String>,
+    pub skipped: Vec<String>, // blank and comment lines; informational, not warnings
+    pub samples: Vec<String>, // names after FORMAT on the `#CHROM` line
+}
+
+/// Lines starting with one of these are skipped as comments unless
+/// `comment_prefixes` is set in the config: UCSC `track` and `browser`
+/// lines that some pipelines put before the header.
+pub const COMMENT_PREFIXES: &[&str] = &["track", "browser"];
+
+/// Whether `line` is a blank line or starts with one of `comments`.
+fn is_skippable(line: &str, comments: &[String]) -> bool {
+    line.trim().is_empty() || comments.iter().any(|c| line.starts_with(c.as_str()))
+}
+
+/// `delimiter` overrides the field separator for VCF text; `None` means
+/// tab, falling back to whitespace for lines with too few tab fields.
+/// Lines starting with one of `comments` are skipped, as are blank ones.
+pub fn parse_vcf(
+    path: &Path,
+    delimiter: Option<char>,
+    comments: &[String],
+    progress: Option<&mut dyn FnMut(u64, u64)>,
+) -> Result<ParsedVcf, Box<dyn std::error::Error>> {
+    if let Some(url) = path.to_str().filter(|_| is_url(path)) {
+        return parse_vcf_url(url, delimiter, comments, progress);
+    }
+    let mut file = File::open(path)?;
+    let total = file.metadata()?.len();
+    let mut magic = [0u8; 3];
+    let n = file.read(&mut magic)?;
+    let magic = &magic[..n];
+    let is_bcf = magic == b"BCF" || path.extension().is_some_and(|ext| ext == "bcf");
+    // Compressed BCF is BGZF, which starts with the gzip magic bytes.
+    let is_gzip = magic.starts_with(&[0x1f, 0x8b]);
+    file.seek(SeekFrom::Start(0))?;
+
+    let mut noop = |_, _| {};
+    let reader = ProgressReader {
+        inner: file,
+        read: 0,
+        total,
+        last_percent: 0,
+        callback: progress.unwrap_or(&mut noop),
+    };
+    if is_bcf {
+        let compression = if is_gzip {
+            bcf::io::CompressionMethod::Bgzf
+        } else {
+            bcf::io::CompressionMethod::None
+        };
+        return parse_bcf(reader, compression);
+    }
+    let mut reader = BufReader::new(reader);
+    check_vcf_text(reader.fill_buf()?, delimiter, comments)?;
+    parse_vcf_delimited(reader, delimiter, comments)
+}
+
+/// Whether `path` is an `http://` or `https://` URL rather than a file.
+pub fn is_url(path: &Path) -> bool {
+    path.to_str()
+        .is_some_and(|s| s.starts_with("http://") || s.starts_with("https://"))
+}
+
+/// Downloads a VCF or BCF, gzipped or not, and parses it like stdin input.
+/// Progress is measured against Content-Length when the server sends it.
+fn parse_vcf_url(
+    url: &str,
+    delimiter: Option<char>,
+    comments: &[String],
+    progress: Option<&mut dyn FnMut(u64, u64)>,
+) -> Result<ParsedVcf, Box<dyn std::error::Error>> {
+    let (body, total) = open_url(url)?;
+    let mut noop = |_, _| {};
+    let reader = ProgressReader {
+        inner: body,
+        read: 0,
+        total,
+        last_percent: 0,
+        callback: progress.unwrap_or(&mut noop),
+    };
+    parse_vcf_stream(BufReader::new(reader), delimiter, comments)
+}
+
+/// Parses VCF or BCF as it is read, decompressing gzip (or BGZF) on the
+/// way, so a download or pipe is never held in memory whole.
+fn parse_vcf_stream(
+    mut reader: impl BufRead,
+    delimiter: Option<char>,
+    comments: &[String],
+) -> Result<ParsedVcf, Box<dyn std::error::Error>> {
+    if reader.fill_buf()?.starts_with(&[0x1f, 0x8b]) {
+        let mut reader = BufReader::new(MultiGzDecoder::new(reader));
+        if reader.fill_buf()?.starts_with(&[0x1f, 0x8b]) {
+            return Err("doesn't look like a VCF (compressed twice)".into());
+        }
+        return parse_uncompressed(reader, delimiter, comments);
+    }
+    parse_uncompressed(reader, delimiter, comments)
+}
+
+/// `parse_vcf_stream` once any gzip layer is off: BCF or VCF text.
+fn parse_uncompressed(
+    mut reader: impl BufRead,
+    delimiter: Option<char>,
+    comments: &[String],
+) -> Result<ParsedVcf, Box<dyn std::error::Error>> {
+    let start = reader.fill_buf()?;
+    if start.starts_with(b"BCF") {
+        return parse_bcf(reader, bcf::io::CompressionMethod::None);
+    }
+    check_vcf_text(start, delimiter, comments)?;
+    parse_vcf_delimited(reader, delimiter, comments)
+}
+
+/// Rejects input whose first line isn't UTF-8 text that starts like a VCF
+/// (`##fileformat`, `#CHROM` or a record line), so a mis-named binary file
+/// isn't parsed into junk. `start` is the beginning of the input; blank and
+/// comment lines before the first real line are passed over.
+fn check_vcf_text(
+    start: &[u8],
+    delimiter: Option<char>,
+    comments: &[String],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let text = match std::str::from_utf8(start) {
+        Ok(text) => text,
+        // Cut off mid-character at the end of the peeked bytes.
+        Err(e) if e.error_len().is_none() => std::str::from_utf8(&start[..e.valid_up_to()])?,
+        Err(_) => return Err("doesn't look like a VCF (not text)".into()),
+    };
+    let Some(line) = text
+        .split('\n')
+        .map(|line| line.trim_end_matches('\r'))
+        .find(|line| !is_skippable(line, comments))
+    else {
+        return Ok(());
+    };
+    let fields = match delimiter {
+        Some(d) => line.split(d).count(),
+        None => line
+            .split('\t')
+            .count()
+            .max(line.split_whitespace().count()),
+    };
+    let plausible = line.starts_with("##fileformat")
+        || line.starts_with("#CHROM")
+        || (!line.starts_with('#') && fields >= 5 && !line.contains('\0'));
+    if plausible {
+        Ok(())
+    } else {
+        Err("doesn't look like a VCF (no ##fileformat or #CHROM header)".into())
+    }
+}
+
+/// Parses an in-memory VCF or BCF stream, e.g. from stdin. Gzip data is
+/// decompressed first, so gzipped VCF and BGZF-compressed BCF (as written
+/// by `bcftools view -Ob`) both load.
+pub fn parse_vcf_bytes(
+    data: &[u8],
+    delimiter: Option<char>,
+    comments: &[String],
+) -> Result<ParsedVcf, Box<dyn std::error::Error>> {
+    parse_vcf_stream(data, delimiter, comments)
+}
+
+/// The VCF text of a VCF or BCF stream, read as it arrives: gzip is
+/// decompressed and BCF decoded, so record `line`s from parsing it index
+/// into it.
+pub fn vcf_text(mut reader: impl BufRead) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    if reader.fill_buf()?.starts_with(&[0x1f, 0x8b]) {
+        // BGZF-compressed BCF or a gzipped (or BGZF) VCF.
+        let mut reader = BufReader::new(MultiGzDecoder::new(reader));
+        if reader.fill_buf()?.starts_with(&[0x1f, 0x8b]) {
+            return Err("doesn't look like a VCF (compressed twice)".into());
+        }
+        return uncompressed_text(reader);
+    }
+    uncompressed_text(reader)
+}
+
+/// `vcf_text` once any gzip layer is off.
+fn uncompressed_text(mut reader: impl BufRead) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    if reader.fill_buf()?.starts_with(b"BCF") {
+        return bcf_text(reader, bcf::io::CompressionMethod::None);
+    }
+    let mut text = Vec::new();
+    reader.read_to_end(&mut text)?;
+    Ok(text)
+}
+
+/// Decodes a BCF file to VCF text and parses that, so both formats go
+/// through the same record path.
+fn parse_bcf<R: Read>(
+    source: R,
+    compression: bcf::io::CompressionMethod,
+) -> Result<ParsedVcf, Box<dyn std::error::Error>> {
+    parse_vcf_reader(bcf_text(source, compression)?.as_slice())
+}
+
+fn bcf_text<R: Read>(
+    source: R,
+    compression: bcf::io::CompressionMethod,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let mut reader = bcf::io::reader::Builder::default()
+        .set_compression_method(compression)
+        .build_from_reader(source)?;
+    let header = reader.read_header()?;
+    let mut writer = vcf::io::Writer::new(Vec::new());
+    writer.write_header(&header)?;
+    for result in reader.records() {
+        let record = result?;
+        writer.write_variant_record(&header, &record)?;
+    }
+    Ok(writer.into_inner())
+}
+
+/// Opens an input for reading as it arrives: a file, or an http(s) URL.
+pub fn open_input(path: &Path) -> Result<Box<dyn Read + Send>, Box<dyn std::error::Error>> {
+    match path.to_str().filter(|_| is_url(path)) {
+        Some(url) => Ok(open_url(url)?.0),
+        None => Ok(Box::new(File::open(path)?)),
+    }
+}
+
+/// The body of a GET of `url` and its Content-Length, 0 if not sent.
+fn open_url(url: &str) -> Result<(Box<dyn Read + Send>, u64), Box<dyn std::error::Error>> {
+    let response = ureq::get(url).call()?;
+    let total = response
+        .header("Content-Length")
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0);
+    Ok((Box::new(response.into_reader()), total))
+}
+
+pub fn parse_vcf_reader<R: BufRead>(reader: R) -> Result<ParsedVcf, Box<dyn std::error::Error>> {
+    parse_vcf_delimited(reader, None, &[])
+}
+
+/// Like `parse_vcf_reader`, but with an explicit `delimiter` every line is
+/// split on it alone, so INFO values may contain spaces. Blank lines and
+/// lines starting with one of `comments` are noted in `skipped`.
+pub fn parse_vcf_delimited<R: BufRead>(
+    reader: R,
+    delimiter: Option<char>,
+    comments: &[String],
+) -> Result<ParsedVcf, Box<dyn std::error::Error>> {
+    let mut parsed = ParsedVcf::default();
+    let mut declared = None; // column count of the `#CHROM` line
+
+    for (n, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.strip_suffix('\r').unwrap_or(&line);
+        if is_skippable(line, comments) {
+            let what = if line.trim().is_empty() {
+                "blank line".to_string()
+            } else {
+                format!("comment '{}'", line.chars().take(60).collect::<String>())
+            };
+            parsed.skipped.push(format!("line {}: {}", n + 1, what));
+            continue;
+        }
+        let mut fields: Vec<&str> = line.split(delimiter.unwrap_or('\t')).collect();
+        if fields.len() < 5 && delimiter.is_none() {
+            // Not tab-delimited; fall back to any run of whitespace.
+            fields = line.split_whitespace().collect();
+        }
+        if line.starts_with('#') {
+            if line.starts_with("#CHROM") {
+                parsed.samples = fields.iter().skip(9).map(|s| s.to_string()).collect();
+                declared = Some(fields.len());
+                // Keep the column line tab-separated so exports are valid VCF.
+                parsed.header.push(fields.join("\t"));
+            } else {
+                parsed.header.push(line.to_string());
+            }
+            continue;
+        }
+        if fields.len() < 5 {
+            parsed.warnings.push(format!(
+                "line {}: only {} columns, line skipped",
+                n + 1,
+                fields.len()
+            ));
+            continue;
+        }
+
+        let Ok(pos) = fields[1].parse::<u64>() else {
+            parsed.warnings.push(format!(
+                "line {}: POS '{}' is not a number, record skipped",
+                n + 1,
+                fields[1]
+            ));
+            continue;
+        };
+        let qual_raw = fields.get(5).copied().unwrap_or(".");
+        let qual = match qual_raw {
+            "." => None,
+            raw => match raw.parse::<f64>() {
+                Ok(q) => Some(q),
+                Err(_) => {
+                    parsed.warnings.push(format!(
+                        "line {}: QUAL '{}' is not a number, treated as missing",
+                        n + 1,
+                        raw
+                    ));
+                    None
+                }
+            },
+        };
+
+        if let Some(declared) = declared
+            && fields.len() > declared
+        {
+            parsed.warnings.push(format!(
+                "line {}: {} columns but the header declares {}",
+                n + 1,
+                fields.len(),
+                declared
+            ));
+        }
+        let problem = validate_record(pos, fields[3], fields[4]);
+        if let Some(problem) = problem {
+            parsed.warnings.push(format!(
+                "line {}: {} ({}:{} {}>{}), record flagged",
+                n + 1,
+                problem,
+                fields[0],
+                fields[1],
+                fields[3],
+                fields[4]
+            ));
+        }
+
+        // Only GT is kept from the sample columns.
+        let genotypes = match fields.get(8).map(|f| f.split(':').position(|k| k == "GT")) {
+            Some(Some(gt)) => fields[9..]
+                .iter()
+                .map(|sample| sample.split(':').nth(gt).unwrap_or(".").to_string())
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        parsed.records.push(VcfRecord {
+            chrom: fields[0].to_string(),
+            pos,
+            id: fields[2].to_string(),
+            ref_: fields[3].to_string(),
+            alt: fields[4].to_string(),
+            qual,
+            qual_raw: qual_raw.to_string(),
+            filter: fields.get(6).unwrap_or(&".").to_string(),
+            info: fields.get(7).unwrap_or(&".").to_string(),
+            genotypes,
+            sample_columns: fields.get(8..).unwrap_or_default().join("\t"),
+            source: None,
+            line: n + 1,
+            problem,
+        });
+    }
+    Ok(parsed)
+}
+
+/// Writes the `#` header lines of `text` and the lines holding `records`
+/// exactly as they are, so nothing (sample columns, number formatting) is
+/// lost; `records` must come from parsing `text`.
+pub fn write_vcf_lines(mut out: impl Write, text: &[u8], records: &[&VcfRecord]) -> io::Result<()> {
+    let wanted: HashSet<usize> = records.iter().map(|r| r.line).collect();
+    for (n, line) in text.split_inclusive(|&b| b == b'\n').enumerate() {
+        if line.starts_with(b"#") || wanted.contains(&(n + 1)) {
+            out.write_all(line)?;
+            if !line.ends_with(b"\n") {
+                out.write_all(b"\n")?;
+            }
+        }
+    }
+    out.flush()
+}
+
+/// Writes `records` as VCF under `header`, with their FORMAT and sample
+/// columns as they were read.
+pub fn write_vcf(mut out: impl Write, header: &[String], records: &[&VcfRecord]) -> io::Result<()> {
+    for line in header {
+        writeln!(out, "{}", line)?;
+    }
+    if !header.iter().any(|l| l.starts_with("#CHROM")) {
+        writeln!(out, "#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO")?;
+    }
+    for r in records {
+        write!(
+            out,
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            r.chrom,
+            r.pos,
+            r.id,
+            r.ref_,
+            r.alt,
+            r.qual_text(),
+            r.filter,
+            r.info
+        )?;
+        if !r.sample_columns.is_empty() {
+            write!(out, "\t{}", r.sample_columns)?;
+        }
+        writeln!(out)?;
+    }
+    out.flush()
+}
+
+/// Records per chromosome in a VCF file, in order of first appearance, from
+/// a scan of the CHROM column alone; `delimiter` and `comments` are as for
+/// `parse_vcf`. Gzipped files are decompressed; BCF is binary and not
+/// scanned. Gives up with an error once `cancel` is set.
+pub fn chrom_counts(
+    path: &Path,
+    delimiter: Option<char>,
+    comments: &[String],
+    cancel: &AtomicBool,
+) -> Result<Vec<(String, usize)>, Box<dyn std::error::Error>> {
+    let mut file = File::open(path)?;
+    let mut magic = [0u8; 3];
+    let n = file.read(&mut magic)?;
+    if &magic[..n] == b"BCF" || path.extension().is_some_and(|ext| ext == "bcf") {
+        return Err("no preview for BCF".into());
+    }
+    file.seek(SeekFrom::Start(0))?;
+    if magic.starts_with(&[0x1f, 0x8b]) {
+        let reader = BufReader::new(MultiGzDecoder::new(file));
+        return Ok(count_chroms(reader, delimiter, comments, cancel)?);
+    }
+    Ok(count_chroms(
+        BufReader::new(file),
+        delimiter,
+        comments,
+        cancel,
+    )?)
+}
+
+/// `chrom_counts` over any reader: the first field of each line the parser
+/// would read as a record.
+fn count_chroms(
+    mut reader: impl BufRead,
+    delimiter: Option<char>,
+    comments: &[String],
+    cancel: &AtomicBool,
+) -> io::Result<Vec<(String, usize)>> {
+    let mut counts: Vec<(String, usize)> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 0 {
+        if cancel.load(atomic::Ordering::Relaxed) {
+            return Err(io::Error::other("cancelled"));
+        }
+        let text = line.trim_end_matches(['\n', '\r']);
+        if !is_skippable(text, comments) && !text.starts_with('#') {
+            let mut fields: Vec<&str> = text.split(delimiter.unwrap_or('\t')).collect();
+            if fields.len() < 5 && delimiter.is_none() {
+                fields = text.split_whitespace().collect();
+            }
+            if fields.len() >= 5 {
+                match index.get(fields[0]) {
+                    Some(&i) => counts[i].1 += 1,
+                    None => {
+                        index.insert(fields[0].to_string(), counts.len());
+                        counts.push((fields[0].to_string(), 1));
+                    }
+                }
+            }
+        }
+        line.clear();
+    }
+    Ok(counts)
+}
+
+pub fn parse_bed(path: &Path) -> Result<BedRegions, Box<dyn std::error::Error>> {
+    let file = File::open(path)?;
+    let reader = BufReader::new(file);
+    let mut regions: HashMap<String, Vec<(u64, u64)>> = HashMap::new();
+
+    for (lineno, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim_end();
+        if line.is_empty()
+            || line.starts_with('#')
+            || line.starts_with("track")
+            || line.starts_with("browser")
+        {
+            continue;
+        }
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() < 3 {
+            return Err(format!("line {}: expected at least 3 columns", lineno + 1).into());
+        }
+        let start: u64 = fields[1]
+            .trim()
+            .parse()
+            .map_err(|_| format!("line {}: invalid start '{}'", lineno + 1, fields[1]))?;
+        let end: u64 = fields[2]
+            .trim()
+            .parse()
+            .map_err(|_| format!("line {}: invalid end '{}'", lineno + 1, fields[2]))?;
+        if end <= start {
+            continue;
+        }
+        regions
+            .entry(fields[0].to_string())
+            .or_default()
+            .push((start, end));
+    }
+
+    for intervals in regions.values_mut() {
+        intervals.sort_unstable();
+        let mut merged: Vec<(u64, u64)> = Vec::with_capacity(intervals.len());
+        for &(start, end) in intervals.iter() {
+            match merged.last_mut() {
+                Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+        *intervals = merged;
+    }
+
+    Ok(BedRegions {
+        path: path.to_owned(),
+        regions,
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VariantType {
+    Snp,
+    Indel,
+    Other,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlleleClass {
+    Snp,
+    Insertion,
+    Deletion,
+    Complex, // MNPs and mixed sites
+    Other,   // symbolic or missing ALT
+}
+
+#[derive(Debug, Default)]
+pub struct VariantStats {
+    pub total: usize,
+    pub per_chrom: Vec<(String, usize)>, // in order of first appearance
+    pub snps: usize,
+    pub indels: usize,
+    pub other: usize,
+    pub transitions: usize,
+    pub transversions: usize,
+}
+
+impl VariantStats {
+    pub fn from_records<'a>(records: impl IntoIterator<Item = &'a VcfRecord>) -> Self {
+        let mut stats = VariantStats::default();
+        let mut chrom_index: HashMap<&str, usize> = HashMap::new();
+        for r in records {
+            stats.total += 1;
+            let i = *chrom_index.entry(&r.chrom).or_insert_with(|| {
+                stats.per_chrom.push((r.chrom.clone(), 0));
+                stats.per_chrom.len() - 1
+            });
+            stats.per_chrom[i].1 += 1;
+
+            match r.variant_type() {
+                VariantType::Snp => stats.snps += 1,
+                VariantType::Indel => stats.indels += 1,
+                VariantType::Other => stats.other += 1,
+            }
+            for alt in r.alt.split(',') {
+                match is_transition(&r.ref_, alt) {
+                    Some(true) => stats.transitions += 1,
+                    Some(false) => stats.transversions += 1,
+                    None => {}
+                }
+            }
+        }
+        stats
+    }
+
+    pub fn ts_tv(&self) -> Option<f64> {
+        (self.transversions > 0).then(|| self.transitions as f64 / self.transversions as f64)
+    }
+}
+
+/// Writes `records` as BED: `chrom`, `pos-1` and `pos-1+len(REF)`, so each
+/// interval covers the reference allele. IDs go in a fourth column when any
+/// record has one, `.` standing in for the rest.
+pub fn write_bed(mut out: impl Write, records: &[&VcfRecord]) -> io::Result<()> {
+    let with_id = records.iter().any(|r| r.id != ".");
+    for r in records {
+        let start = r.pos.saturating_sub(1);
+        let end = start + r.ref_.len().max(1) as u64;
+        if with_id {
+            writeln!(out, "{}\t{}\t{}\t{}", r.chrom, start, end, r.id)?;
+        } else {
+            writeln!(out, "{}\t{}\t{}", r.chrom, start, end)?;
+        }
+    }
+    out.flush()
+}
+
+/// Genotype calls of one sample, by zygosity.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct GtCounts {
+    pub hom_ref: usize,
+    pub het: usize,
+    pub hom_alt: usize,
+    pub missing: usize, // `.` in any allele, or no GT at all
+}
+
+impl GtCounts {
+    /// Tallies the GT of sample column `sample` across `records`.
+    pub fn from_records<'a>(
+        records: impl IntoIterator<Item = &'a VcfRecord>,
+        sample: usize,
+    ) -> Self {
+        let mut counts = GtCounts::default();
+        for r in records {
+            let gt = r.genotypes.get(sample).map_or(".", String::as_str);
+            let alleles: Vec<&str> = gt.split(['/', '|']).collect();
+            if alleles.iter().any(|a| *a == "." || a.is_empty()) {
+                counts.missing += 1;
+            } else if alleles.iter().all(|a| *a == "0") {
+                counts.hom_ref += 1;
+            } else if alleles.iter().all(|a| *a == alleles[0]) {
+                counts.hom_alt += 1;
+            } else {
+                counts.het += 1;
+            }
+        }
+        counts
+    }
+}
+
+/// `Some(true)` for a transition (A<->G, C<->T), `Some(false)` for a
+/// transversion, `None` if `ref_`/`alt` is not a single-base substitution.
+fn is_transition(ref_: &str, alt: &str) -> Option<bool> {
+    let base = |s: &str| match s.to_ascii_uppercase().as_str() {
+        b @ ("A" | "C" | "G" | "T") => b.chars().next(),
+        _ => None,
+    };
+    let (a, b) = (base(ref_)?, base(alt)?);
+    if a == b {
+        return None;
+    }
+    let purine = |c: char| c == 'A' || c == 'G';
+    Some(purine(a) == purine(b))
+}
+
+#[derive(Debug, PartialEq)]
+pub enum PosRange {
+    None,
+    Exact(u64),
+    Range(u64, u64),
+}
+
+/// Parses `12345`, `1000-5000`, or an open-ended `1000-` / `-5000`, where
+/// the missing bound runs to the end (or start) of the chromosome.
+pub fn parse_pos_range(input: &str) -> PosRange {
+    let s = input.trim();
+    if s.is_empty() {
+        return PosRange::None;
+    }
+
+    if let Ok(pos) = s.parse::<u64>() {
+        return PosRange::Exact(pos);
+    }
+
+    if let Some((start_str, end_str)) = s.split_once('-') {
+        let bound = |s: &str, open: u64| match s.trim() {
+            "" => Ok(open),
+            s => s.parse::<u64>(),
+        };
+        let start = bound(start_str, 0);
+        let end = bound(end_str, u64::MAX);
+        if let (Ok(start), Ok(end)) = (start, end)
+            && start <= end
+            && (start, end) != (0, u64::MAX)
+        {
+            return PosRange::Range(start, end);
+        }
+    }
+
+    PosRange::None
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CmpOp {
+    Lt,
+    Le,
+    Eq,
+    Ne,
+    Ge,
+    Gt,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Comparison {
+    op: CmpOp,
+    pub value: f64,
+}
+
+impl Comparison {
+    pub fn matches(&self, x: f64) -> bool {
+        match self.op {
+            CmpOp::Lt => x < self.value,
+            CmpOp::Le => x <= self.value,
+            CmpOp::Eq => x == self.value,
+            CmpOp::Ne => x != self.value,
+            CmpOp::Ge => x >= self.value,
+            CmpOp::Gt => x > self.value,
+        }
+    }
+}
+
+/// `Number` of each `##INFO` header line by ID, e.g. `AF` -> `A`.
+pub fn info_numbers(header: &[String]) -> HashMap<String, String> {
+    header
+        .iter()
+        .filter_map(|line| line.strip_prefix("##INFO=<"))
+        .filter_map(|fields| {
+            // ID and Number come before the quoted Description, so a plain
+            // split on commas is enough to reach them.
+            let mut id = None;
+            let mut number = None;
+            for field in fields.split(',') {
+                match field.split_once('=') {
+                    Some(("ID", v)) => id = Some(v),
+                    Some(("Number", v)) => number = Some(v),
+                    _ => {}
+                }
+                if id.is_some() && number.is_some() {
+                    break;
+                }
+            }
+            Some((id?.to_string(), number?.to_string()))
+        })
+        .collect()
+}
+
+/// Splits an INFO column into `(key, value)` pairs; flags have no value.
+/// A missing INFO (`.`) gives nothing.
+pub fn parse_info(info: &str) -> Vec<(&str, Option<&str>)> {
+    info.split(';')
+        .filter(|entry| !entry.is_empty() && *entry != ".")
+        .map(|entry| match entry.split_once('=') {
+            Some((k, v)) => (k, Some(v)),
+            None => (entry, None),
+        })
+        .collect()
+}
+
+/// Undoes the VCF percent-encoding of reserved characters (`%3A` is `:`,
+/// `%3B` `;`, `%3D` `=`, `%2C` `,`, `%09` tab, `%0A` newline, `%25` `%`).
+/// Meant for one field value, after the line and INFO have been split;
+/// a `%` not followed by two hex digits is kept as is.
+pub fn percent_decode(value: &str) -> Cow<'_, str> {
+    if !value.contains('%') {
+        return Cow::Borrowed(value);
+    }
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            // from_str_radix alone would take a sign, as in `%+1`.
+            .filter(|h| h.iter().all(u8::is_ascii_hexdigit))
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                out.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    Cow::Owned(String::from_utf8_lossy(&out).into_owned())
+}
+
+/// Parses expressions like `>=5`, `< 10` or `3` (equality).
+pub fn parse_comparison(input: &str) -> Option<Comparison> {
+    let s = input.trim();
+    if s.is_empty() {
+        return None;
+    }
+    let (op, rest) = [
+        (">=", CmpOp::Ge),
+        ("<=", CmpOp::Le),
+        ("!=", CmpOp::Ne),
+        ("==", CmpOp::Eq),
+        (">", CmpOp::Gt),
+        ("<", CmpOp::Lt),
+        ("=", CmpOp::Eq),
+    ]
+    .iter()
+    .find_map(|(prefix, op)| s.strip_prefix(prefix).map(|rest| (*op, rest)))
+    .unwrap_or((CmpOp::Eq, s));
+    let value = rest.trim().parse::<f64>().ok()?;
+    Some(Comparison { op, value })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn parse_vcf_strips_crlf() {
+        let data = "##fileformat=VCFv4.2\r\n\
+                    #CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\r\n\
+                    20\t14370\trs6054257\tG\tA\t29\tPASS\tNS=3;DP=14\r\n\
+                    20\t17330\t.\tT\tA\t3\tq10\tNS=3;DP=11\r\n";
+        let records = parse_vcf_reader(Cursor::new(data)).unwrap().records;
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].info, "NS=3;DP=14");
+        assert_eq!(records[1].info, "NS=3;DP=11");
+    }
+
+    #[test]
+    fn parse_vcf_falls_back_to_whitespace() {
+        let data = "#CHROM POS ID REF ALT QUAL FILTER INFO\n\
+                    20 14370 rs6054257 G A 29 PASS NS=3\n\
+                    X  10    .         AC A  .  .    .\n";
+        let records = parse_vcf_reader(Cursor::new(data)).unwrap().records;
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].chrom, "20");
+        assert_eq!(records[0].pos, 14370);
+        assert_eq!(records[0].info, "NS=3");
+        assert_eq!(records[1].ref_, "AC");
+        assert_eq!(records[1].alt, "A");
+    }
+
+    #[test]
+    fn parse_vcf_reads_samples_and_flags_extra_columns() {
+        let data = "#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tNA1\tNA2\n\
+                    1\t10\t.\tA\tG\t.\t.\t.\tGT\t0/1\t1/1\n\
+                    1\t20\t.\tA\tG\t.\t.\t.\tGT\t0/1\t1/1\t0/0\n";
+        let parsed = parse_vcf_reader(Cursor::new(data)).unwrap();
+        assert_eq!(parsed.samples, ["NA1", "NA2"]);
+        assert_eq!(parsed.records.len(), 2);
+        assert_eq!(parsed.records[0].genotypes, ["0/1", "1/1"]);
+        assert_eq!(
+            parsed.warnings,
+            ["line 3: 12 columns but the header declares 11"]
+        );
+    }
+
+    #[test]
+    fn parse_vcf_skips_blank_and_comment_lines() {
+        let data = "track name=calls\n\
+                    ##fileformat=VCFv4.2\n\
+                    \n\
+                    1\t10\t.\tA\tG\t.\t.\t.\n\
+                    1\t20\n";
+        let comments = ["track".to_string()];
+        let parsed = parse_vcf_bytes(data.as_bytes(), None, &comments).unwrap();
+        assert_eq!(parsed.records.len(), 1);
+        assert_eq!(
+            parsed.skipped,
+            ["line 1: comment 'track name=calls'", "line 3: blank line"]
+        );
+        assert_eq!(parsed.warnings, ["line 5: only 2 columns, line skipped"]);
+        assert!(parse_vcf_bytes(data.as_bytes(), None, &[]).is_err());
+    }
+
+    #[test]
+    fn parse_vcf_splits_on_explicit_delimiter() {
+        let data = "1,10,.,A,G,.,.,NOTE=two words\n";
+        let records = parse_vcf_delimited(Cursor::new(data), Some(','), &[])
+            .unwrap()
+            .records;
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].info, "NOTE=two words");
+    }
+
+    #[test]
+    fn sv_records_label_span_and_type() {
+        let data = "1\t100\t.\tN\t<DEL>\t.\t.\tSVTYPE=DEL;END=5000\n\
+                    2\t300\t.\tN\t<DUP:TANDEM>\t.\t.\tSVLEN=250\n\
+                    3\t10\t.\tA\tG\t.\t.\t.\n";
+        let records = parse_vcf_reader(Cursor::new(data)).unwrap().records;
+        assert_eq!(
+            records[0].sv_label("100").as_deref(),
+            Some("1:100-5000 DEL")
+        );
+        assert_eq!(records[1].sv_label("300").as_deref(), Some("2:300-550 DUP"));
+        assert_eq!(records[2].sv_type(), None);
+    }
+
+    #[test]
+    fn parse_pos_range_accepts_exact_and_ranges() {
+        assert_eq!(parse_pos_range(" 100 "), PosRange::Exact(100));
+        assert_eq!(parse_pos_range("100-200"), PosRange::Range(100, 200));
+        assert_eq!(parse_pos_range("200-100"), PosRange::None);
+        assert_eq!(parse_pos_range("1000-"), PosRange::Range(1000, u64::MAX));
+        assert_eq!(parse_pos_range(" -5000"), PosRange::Range(0, 5000));
+        assert_eq!(parse_pos_range("-"), PosRange::None);
+        assert_eq!(parse_pos_range(""), PosRange::None);
+    }
+
+    #[test]
+    fn parse_vcf_warns_on_non_numeric_fields() {
+        let data = "1\t10\t.\tA\tG\tabc\t.\t.\n\
+                    1\tx\t.\tA\tG\t5\t.\t.\n\
+                    1\t30\t.\tA\tG\t7.5\t.\t.\n";
+        let parsed = parse_vcf_reader(Cursor::new(data)).unwrap();
+        assert_eq!(parsed.records.len(), 2);
+        assert_eq!(parsed.records[0].qual, None);
+        assert_eq!(parsed.records[0].qual_text(), "abc");
+        assert_eq!(parsed.records[1].pos, 30);
+        assert_eq!(parsed.records[1].line, 3);
+        assert_eq!(parsed.records[1].qual, Some(7.5));
+        assert_eq!(parsed.warnings.len(), 2);
+        assert!(parsed.warnings[1].starts_with("line 2: POS"));
+    }
+
+    #[test]
+    fn validate_flags_malformed_records() {
+        let data = "1\t10\t.\t.\tG\t.\t.\t.\n\
+                    1\t0\t.\tA\tG\t.\t.\t.\n\
+                    1\t30\t.\tAXG\tG\t.\t.\t.\n\
+                    1\t40\t.\tacgt\t.\t.\t.\t.\n";
+        let parsed = parse_vcf_reader(Cursor::new(data)).unwrap();
+        let problems: Vec<_> = parsed.records.iter().map(|r| r.problem).collect();
+        assert_eq!(
+            problems,
+            [
+                Some("REF is empty"),
+                Some("POS is not a positive integer"),
+                Some("REF has characters other than ACGTN"),
+                None
+            ]
+        );
+        assert_eq!(parsed.warnings.len(), 3);
+    }
+
+    #[test]
+    fn stats_count_types_and_ts_tv() {
+        let data = "1\t10\t.\tA\tG\t.\t.\t.\n\
+                    1\t20\t.\tC\tA\t.\t.\t.\n\
+                    2\t30\t.\tAT\tA\t.\t.\t.\n\
+                    2\t40\t.\tA\t<DEL>\t.\t.\t.\n";
+        let records = parse_vcf_reader(Cursor::new(data)).unwrap().records;
+        let stats = VariantStats::from_records(&records);
+        assert_eq!(stats.total, 4);
+        assert_eq!((stats.snps, stats.indels, stats.other), (2, 1, 1));
+        assert_eq!((stats.transitions, stats.transversions), (1, 1));
+        assert_eq!(stats.ts_tv(), Some(1.0));
+        assert_eq!(
+            stats.per_chrom,
+            vec![("1".to_string(), 2), ("2".to_string(), 2)]
+        );
+    }
+
+    #[test]
+    fn write_bed_covers_ref_and_adds_ids() {
+        let data = "1\t100\trs1\tA\tG\t.\t.\t.\n\
+                    2\t200\t.\tATT\tA\t.\t.\t.\n";
+        let records = parse_vcf_reader(Cursor::new(data)).unwrap().records;
+        let mut out = Vec::new();
+        write_bed(&mut out, &records.iter().collect::<Vec<_>>()).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "1\t99\t100\trs1\n2\t199\t202\t.\n"
+        );
+        let mut out = Vec::new();
+        write_bed(&mut out, &[&records[1]]).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "2\t199\t202\n");
+    }
+
+    #[test]
+    fn genotype_counts_by_zygosity() {
+        let data = "1\t10\t.\tA\tG\t.\t.\t.\tGT:DP\t0/0:3\t1|1\n\
+                    1\t20\t.\tA\tG,T\t.\t.\t.\tDP:GT\t5:1/2\t./.\n\
+                    1\t30\t.\tA\tG\t.\t.\t.\tGT\t0|1\t1\n\
+                    1\t40\t.\tA\tG\t.\t.\t.\tDP\t4\t6\n";
+        let records = parse_vcf_reader(Cursor::new(data)).unwrap().records;
+        let counts = |sample| GtCounts::from_records(&records, sample);
+        assert_eq!(
+            counts(0),
+            GtCounts {
+                hom_ref: 1,
+                het: 2,
+                hom_alt: 0,
+                missing: 1
+            }
+        );
+        assert_eq!(
+            counts(1),
+            GtCounts {
+                hom_ref: 0,
+                het: 0,
+                hom_alt: 2,
+                missing: 2
+            }
+        );
+    }
+
+    #[test]
+    fn allele_class_splits_indels_by_direction() {
+        let data = "1\t10\t.\tA\tG,T\t.\t.\t.\n\
+                    1\t20\t.\tA\tAT\t.\t.\t.\n\
+                    1\t30\t.\tAT\tA\t.\t.\t.\n\
+                    1\t40\t.\tAT\tGC\t.\t.\t.\n\
+                    1\t50\t.\tA\tG,AT\t.\t.\t.\n\
+                    1\t60\t.\tA\t<DEL>\t.\t.\t.\n";
+        let records = parse_vcf_reader(Cursor::new(data)).unwrap().records;
+        let classes: Vec<AlleleClass> = records.iter().map(|r| r.allele_class()).collect();
+        assert_eq!(
+            classes,
+            [
+                AlleleClass::Snp,
+                AlleleClass::Insertion,
+                AlleleClass::Deletion,
+                AlleleClass::Complex,
+                AlleleClass::Complex,
+                AlleleClass::Other
+            ]
+        );
+    }
+
+    #[test]
+    fn gt_alleles_spell_out_indexes() {
+        let data = "1\t10\t.\tA\tT,C\t.\t.\t.\n";
+        let parsed = parse_vcf_reader(Cursor::new(data)).unwrap();
+        let r = &parsed.records[0];
+        assert_eq!(r.gt_alleles("0/1"), "A/T");
+        assert_eq!(r.gt_alleles("1|2"), "T|C");
+        assert_eq!(r.gt_alleles("./."), "./.");
+        assert_eq!(r.gt_alleles("1"), "T");
+        assert_eq!(r.gt_alleles("0/5"), "A/5");
+    }
+
+    #[test]
+    fn percent_decode_reserved_characters() {
+        assert_eq!(percent_decode("a%3Ab%3Bc%3Dd%2Ce"), "a:b;c=d,e");
+        assert_eq!(percent_decode("x%09y%0Az%25"), "x\ty\nz%");
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz%4"), "%zz%4");
+        assert_eq!(percent_decode("%+1%-1"), "%+1%-1");
+        assert!(matches!(percent_decode("plain"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn write_vcf_keeps_qual_as_written() {
+        let data = "##fileformat=VCFv4.2\n\
+                    #CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n\
+                    1\t10\t.\tA\tG\t29.0\tPASS\t.\n\
+                    1\t20\t.\tA\tG\t1e3\t.\t.\n";
+        let parsed = parse_vcf_reader(Cursor::new(data)).unwrap();
+        assert_eq!(parsed.records[1].qual, Some(1000.0));
+        let mut out = Vec::new();
+        write_vcf(
+            &mut out,
+            &parsed.header,
+            &parsed.records.iter().collect::<Vec<_>>(),
+        )
+        .unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), data);
+    }
+
+    #[test]
+    fn write_vcf_keeps_sample_columns() {
+        let data = include_str!("../sample/sample.vcf");
+        let parsed = parse_vcf_reader(Cursor::new(data)).unwrap();
+        let mut out = Vec::new();
+        write_vcf(
+            &mut out,
+            &parsed.header,
+            &parsed.records.iter().collect::<Vec<_>>(),
+        )
+        .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("\tFORMAT\tNA00001\tNA00002\tNA00003\n"));
+        assert!(out.contains("\tGT:GQ:DP:HQ\t0|0:48:1:51,51\t1|0:48:8:51,51\t1/1:43:5:.,.\n"));
+    }
+
+    #[test]
+    fn info_values_line_up_with_alleles() {
+        let header = [
+            "##INFO=<ID=AF,Number=A,Type=Float,Description=\"Allele Frequency, per ALT\">".into(),
+            "##INFO=<ID=AD,Number=R,Type=Integer,Description=\"Depth\">".into(),
+            "##INFO=<ID=DP,Number=1,Type=Integer,Description=\"Depth\">".into(),
+        ];
+        let numbers = info_numbers(&header);
+        assert_eq!(numbers["AF"], "A");
+        assert_eq!(numbers["DP"], "1");
+
+        let data = "1\t10\t.\tA\tG,T\t.\t.\tAF=0.1,0.2;AD=5,3,2\n";
+        let r = &parse_vcf_reader(Cursor::new(data)).unwrap().records[0];
+        assert_eq!(
+            r.allele_values("A", "0.1,0.2"),
+            Some(vec![("G", "0.1"), ("T", "0.2")])
+        );
+        assert_eq!(
+            r.allele_values("R", "5,3,2"),
+            Some(vec![("A", "5"), ("G", "3"), ("T", "2")])
+        );
+        assert_eq!(r.allele_values("A", "0.1"), None);
+        assert_eq!(r.allele_values("1", "5"), None);
+    }
+
+    #[test]
+    fn info_value_finds_values_and_flags() {
+        let data = "1\t10\t.\tA\tG\t.\t.\tDP=14;DB;AA=\n1\t20\t.\tA\tG\t.\t.\t.\n";
+        let parsed = parse_vcf_reader(Cursor::new(data)).unwrap();
+        let r = &parsed.records[0];
+        assert_eq!(r.info_value("DP"), Some("14"));
+        assert_eq!(r.info_value("DB"), Some(""));
+        assert_eq!(r.info_value("AA"), Some(""));
+        assert_eq!(r.info_value("D"), None);
+        assert_eq!(parsed.records[1].info_value("."), None);
+    }
+
+    #[test]
+    fn allele_frequencies_keep_their_allele_index() {
+        let data = "1\t10\t.\tA\tG,T,C\t.\t.\tDP=3;AF=0.1,.,0.3\n";
+        let parsed = parse_vcf_reader(Cursor::new(data)).unwrap();
+        assert_eq!(
+            parsed.records[0].allele_frequencies(),
+            [Some(0.1), None, Some(0.3)]
+        );
+    }
+
+    #[test]
+    fn count_chroms_tallies_the_first_column() {
+        let data = "##fileformat=VCFv4.2\n#CHROM\tPOS\n\
+                    1\t10\t.\tA\tG\n1\t20\t.\tA\tG\n\nX\t5\t.\tC\tT\n2 7 . A G\n1\t30\t.\tA\tG\n";
+        let counts = count_chroms(Cursor::new(data), None, &[], &AtomicBool::new(false)).unwrap();
+        assert_eq!(
+            counts,
+            [
+                ("1".to_string(), 3),
+                ("X".to_string(), 1),
+                ("2".to_string(), 1)
+            ]
+        );
+        let data = "track name=x\n1,10,.,A,G\n2,5,.,C,T\n";
+        let comments = ["track".to_string()];
+        let counts = count_chroms(
+            Cursor::new(data),
+            Some(','),
+            &comments,
+            &AtomicBool::new(false),
+        )
+        .unwrap();
+        assert_eq!(counts, [("1".to_string(), 1), ("2".to_string(), 1)]);
+        assert!(count_chroms(Cursor::new(data), None, &[], &AtomicBool::new(true)).is_err());
+    }
+
+    #[test]
+    fn parse_vcf_bytes_decompresses_gzip() {
+        use flate2::{Compression, write::GzEncoder};
+
+        let mut gz = GzEncoder::new(Vec::new(), Compression::default());
+        gz.write_all(b"##fileformat=VCFv4.2\n1\t10\t.\tA\tG\t.\t.\t.\n")
+            .unwrap();
+        let gz = gz.finish().unwrap();
+        let parsed = parse_vcf_bytes(&gz, None, &[]).unwrap();
+        assert_eq!(parsed.records.len(), 1);
+        // As from a download, read a few bytes at a time.
+        let parsed = parse_vcf_stream(BufReader::with_capacity(4, &gz[..]), None, &[]).unwrap();
+        assert_eq!(parsed.records.len(), 1);
+    }
+
+    #[test]
+    fn parse_vcf_downloads_urls() {
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/x.vcf", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let body = "##fileformat=VCFv4.2\n1\t10\t.\tA\tG\t.\t.\t.\n";
+            let (mut stream, _) = listener.accept().unwrap();
+            let _ = stream.read(&mut [0u8; 1024]);
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            )
+            .unwrap();
+        });
+        assert!(is_url(Path::new(&url)));
+        let parsed = parse_vcf(Path::new(&url), None, &[], None).unwrap();
+        server.join().unwrap();
+        assert_eq!(parsed.records[0].pos, 10);
+    }
+
+    #[test]
+    fn parse_vcf_bytes_rejects_non_vcf() {
+        let err = parse_vcf_bytes(b"\x89PNG\r\n\x1a\n\xff\xfe", None, &[]).unwrap_err();
+        assert!(err.to_string().starts_with("doesn't look like a VCF"));
+        assert!(parse_vcf_bytes(b"hello world\n", None, &[]).is_err());
+        assert!(parse_vcf_bytes(b"##fileformat=VCFv4.2\n", None, &[]).is_ok());
+        assert!(parse_vcf_bytes(b"1\t10\t.\tA\tG\n", None, &[]).is_ok());
+    }
+}